@@ -0,0 +1,531 @@
+use crate::simulation::{Directories, Options};
+use crate::{http, network};
+use clap::{App, Arg, ArgMatches, Error, ErrorKind};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, thread, time::Duration};
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+
+/// What the server does with its clients.
+pub enum Mode {
+    /// Run the simulation.
+    Simulate(Box<Options>),
+
+    /// Stream the recording at the given path instead of running the
+    /// simulation.
+    Replay(PathBuf),
+
+    /// Run the simulations of a parameter sweep without listening for
+    /// clients, then exit.
+    Sweep {
+        /// File that defines the sweep.
+        file: PathBuf,
+
+        /// Directory to which the summary and the outputs of each run are
+        /// written.
+        output_dir: PathBuf,
+
+        /// Number of runs simulated at the same time.
+        jobs: usize,
+
+        /// Options shared by every run.
+        options: Box<Options>,
+    },
+}
+
+/// Configuration of the server read from the command line.
+pub struct Config {
+    /// Address on which to listen for client connections.
+    pub addr: SocketAddr,
+
+    /// If true then the simulation runs without listening for clients.
+    pub headless: bool,
+
+    /// Configuration of the HTTP server, or `None` if it is disabled.
+    pub http: Option<http::Options>,
+
+    /// Directives that select which spans and events are logged, in the
+    /// format of `RUST_LOG`.
+    pub log_filter: String,
+
+    /// If true then log messages are written as JSON objects, one per line.
+    pub log_json: bool,
+
+    pub mode: Mode,
+    pub network: network::Options,
+}
+
+impl Config {
+    /// Reads the configuration from the command line arguments. Exits the
+    /// process with a usage message if the arguments are invalid.
+    pub fn from_args() -> Config {
+        let matches = app(true).get_matches();
+        Config::from_matches(&matches).unwrap_or_else(|err| err.exit())
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Config, Error> {
+        let tick_rate: f64 = parse(matches, "tick-rate");
+        let broadcast_rate: f64 = matches
+            .value_of("broadcast-rate")
+            .map_or(tick_rate, |_| parse(matches, "broadcast-rate"));
+        if broadcast_rate > tick_rate {
+            return Err(Error::with_description(
+                &format!(
+                    "The broadcast rate ({} Hz) must not exceed the tick rate ({} Hz)",
+                    broadcast_rate, tick_rate
+                ),
+                ErrorKind::ValueValidation,
+            ));
+        }
+
+        let defaults = Directories::default();
+        let dir = |name, default| matches.value_of(name).map_or(default, PathBuf::from);
+        let scenario_dir = dir("scenario-dir", defaults.scenarios);
+        let options = Box::new(Options {
+            scenario_file: matches.value_of("scenario").map(PathBuf::from),
+            save_file: matches.value_of("load").map(PathBuf::from),
+            exit_save_file: matches.value_of("save-on-exit").map(PathBuf::from),
+            record_file: matches.value_of("record").map(PathBuf::from),
+            analytics_file: matches.value_of("analytics").map(PathBuf::from),
+            trajectory_file: matches.value_of("trajectories").map(PathBuf::from),
+            trajectory_interval: parse(matches, "trajectory-interval"),
+            cluster_distance: matches
+                .value_of("cluster-distance")
+                .map(|_| parse(matches, "cluster-distance")),
+            seed: matches.value_of("seed").map(|_| parse(matches, "seed")),
+            frame_duration: Duration::from_secs_f64(1.0 / tick_rate),
+            broadcast_interval: (tick_rate / broadcast_rate).round() as u64,
+            realtime: !matches.is_present("headless"),
+            max_frames: matches.value_of("frames").map(|_| parse(matches, "frames")),
+            diagnostics: matches.is_present("diagnostics"),
+            dirs: Directories {
+                saves: dir("save-dir", defaults.saves),
+                recordings: dir("recording-dir", defaults.recordings),
+                scenarios: scenario_dir.clone(),
+            },
+        });
+        let mode = if let Some(path) = matches.value_of("replay") {
+            Mode::Replay(PathBuf::from(path))
+        } else if let Some(path) = matches.value_of("sweep") {
+            Mode::Sweep {
+                file: PathBuf::from(path),
+                output_dir: dir("sweep-output", PathBuf::from("sweeps")),
+                jobs: matches.value_of("jobs").map_or_else(
+                    || thread::available_parallelism().map_or(1, usize::from),
+                    |_| parse(matches, "jobs"),
+                ),
+                options,
+            }
+        } else {
+            Mode::Simulate(options)
+        };
+
+        Ok(Config {
+            addr: parse(matches, "bind"),
+            headless: matches.is_present("headless"),
+            http: matches.value_of("http-bind").map(|_| http::Options {
+                addr: parse(matches, "http-bind"),
+                static_dir: matches.value_of("static-dir").map(PathBuf::from),
+                scenario_dir,
+            }),
+            log_filter: match matches.value_of("log-filter") {
+                Some(filter) => filter.to_string(),
+                None => parse::<Level>(matches, "log-level").to_string(),
+            },
+            log_json: matches.value_of("log-format") == Some("json"),
+            mode,
+            network: network::Options {
+                idle_timeout: Duration::from_secs_f64(parse(matches, "idle-timeout")),
+                controller_tokens: matches
+                    .values_of("controller-token")
+                    .map_or_else(Vec::new, |tokens| tokens.map(String::from).collect()),
+                compression: !matches.is_present("no-compression"),
+                tls: matches.value_of("tls-cert").map(|cert| network::TlsFiles {
+                    cert: PathBuf::from(cert),
+                    key: PathBuf::from(matches.value_of("tls-key").expect("Key is required.")),
+                }),
+                auth: network::Auth {
+                    tokens: matches
+                        .values_of("auth-token")
+                        .map_or_else(Vec::new, |tokens| tokens.map(String::from).collect()),
+                    secret: matches
+                        .value_of("auth-secret")
+                        .map(|secret| secret.as_bytes().to_vec()),
+                },
+            },
+        })
+    }
+}
+
+/// Builds the command line interface. Some options fall back to environment
+/// variables if `read_env` is true.
+fn app(read_env: bool) -> App<'static, 'static> {
+    let env = |arg: Arg<'static, 'static>, name| if read_env { arg.env(name) } else { arg };
+    App::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Runs an agent-based sheep herd simulation and streams it to WebSocket clients.")
+        .arg(
+            Arg::with_name("bind")
+                .short("b")
+                .long("bind")
+                .value_name("ADDR")
+                .default_value("127.0.0.1:8080")
+                .validator(is_valid::<SocketAddr>)
+                .help("Address on which to listen for clients"),
+        )
+        .arg(
+            Arg::with_name("http-bind")
+                .long("http-bind")
+                .value_name("ADDR")
+                .validator(is_valid::<SocketAddr>)
+                .conflicts_with("headless")
+                .help("Address on which to serve the HTTP API and static files"),
+        )
+        .arg(
+            Arg::with_name("static-dir")
+                .long("static-dir")
+                .value_name("DIR")
+                .requires("http-bind")
+                .help("Directory of static files, such as a browser viewer, served over HTTP"),
+        )
+        .arg(
+            Arg::with_name("idle-timeout")
+                .long("idle-timeout")
+                .value_name("SECS")
+                .default_value("30")
+                .validator(is_positive_number)
+                .help("Time after which a client that has sent nothing is disconnected"),
+        )
+        .arg(
+            Arg::with_name("no-compression")
+                .long("no-compression")
+                .help("Send uncompressed text frames even to clients that ask for compression"),
+        )
+        .arg(
+            Arg::with_name("tls-cert")
+                .long("tls-cert")
+                .value_name("FILE")
+                .requires("tls-key")
+                .help("PEM certificate chain with which clients connect over wss://"),
+        )
+        .arg(
+            Arg::with_name("tls-key")
+                .long("tls-key")
+                .value_name("FILE")
+                .requires("tls-cert")
+                .help("PEM PKCS #8 private key for the TLS certificate"),
+        )
+        .arg(
+            Arg::with_name("auth-token")
+                .long("auth-token")
+                .value_name("TOKEN")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Token that clients must present to connect"),
+        )
+        .arg(env(
+            Arg::with_name("auth-secret")
+                .long("auth-secret")
+                .value_name("SECRET")
+                .hide_env_values(true)
+                .help("Secret with which the tokens that clients present to connect are signed"),
+            "ABM_AUTH_SECRET",
+        ))
+        .arg(
+            Arg::with_name("controller-token")
+                .long("controller-token")
+                .value_name("TOKEN")
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "Token that clients present with ?token= to become controllers. \
+                     If no tokens are given then every client is a controller",
+                ),
+        )
+        .arg(
+            Arg::with_name("scenario")
+                .long("scenario")
+                .value_name("FILE")
+                .help("Scenario file from which the world is initialized"),
+        )
+        .arg(
+            Arg::with_name("load")
+                .long("load")
+                .value_name("FILE")
+                .help("Save file from which the world is loaded before the first frame"),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .value_name("FILE")
+                .conflicts_with_all(&[
+                    "scenario",
+                    "load",
+                    "record",
+                    "analytics",
+                    "trajectories",
+                    "cluster-distance",
+                    "save-on-exit",
+                    "seed",
+                    "headless",
+                    "frames",
+                ])
+                .help("Recording to stream to clients instead of running the simulation"),
+        )
+        .arg(
+            Arg::with_name("sweep")
+                .long("sweep")
+                .value_name("FILE")
+                .conflicts_with_all(&[
+                    "replay",
+                    "scenario",
+                    "load",
+                    "record",
+                    "analytics",
+                    "trajectories",
+                    "save-on-exit",
+                    "seed",
+                    "headless",
+                    "frames",
+                    "http-bind",
+                ])
+                .help("Parameter sweep whose simulations are run in parallel without listening for clients"),
+        )
+        .arg(
+            Arg::with_name("sweep-output")
+                .long("sweep-output")
+                .value_name("DIR")
+                .requires("sweep")
+                .help("Directory to which the summary of each run of the sweep is written [default: sweeps]"),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .value_name("N")
+                .requires("sweep")
+                .validator(is_positive_integer)
+                .help("Number of runs of the sweep simulated at the same time [default: the number of CPUs]"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("N")
+                .validator(is_valid::<u64>)
+                .help("Seed for the random number generator"),
+        )
+        .arg(
+            Arg::with_name("tick-rate")
+                .long("tick-rate")
+                .value_name("HZ")
+                .default_value("31.25")
+                .validator(is_positive_number)
+                .help("Number of frames simulated per second"),
+        )
+        .arg(
+            Arg::with_name("broadcast-rate")
+                .long("broadcast-rate")
+                .value_name("HZ")
+                .validator(is_positive_number)
+                .help("Number of world state broadcasts per second [default: the tick rate]"),
+        )
+        .arg(
+            Arg::with_name("headless")
+                .long("headless")
+                .help("Run the simulation as quickly as possible without listening for clients"),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .long("frames")
+                .value_name("N")
+                .validator(is_valid::<u64>)
+                .help("Number of frames after which the simulation stops"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .value_name("FILE")
+                .help("Recording file to which every frame is written"),
+        )
+        .arg(
+            Arg::with_name("analytics")
+                .long("analytics")
+                .value_name("FILE")
+                .help("CSV file to which the statistics of the flock are written every frame"),
+        )
+        .arg(
+            Arg::with_name("trajectories")
+                .long("trajectories")
+                .value_name("FILE")
+                .help("CSV file to which the position, heading, velocity and behavior of every agent are written"),
+        )
+        .arg(
+            Arg::with_name("trajectory-interval")
+                .long("trajectory-interval")
+                .value_name("FRAMES")
+                .default_value("1")
+                .validator(is_positive_integer)
+                .help("Number of frames between the samples written to the trajectory file"),
+        )
+        .arg(
+            Arg::with_name("cluster-distance")
+                .long("cluster-distance")
+                .value_name("METERS")
+                .validator(is_positive_number)
+                .help(
+                    "Distance within which sheep are in the same cluster \
+                     [default: the cell size of the world map]",
+                ),
+        )
+        .arg(
+            Arg::with_name("save-on-exit")
+                .long("save-on-exit")
+                .value_name("FILE")
+                .help("Save file to which the world is written when the simulation stops"),
+        )
+        .arg(
+            Arg::with_name("save-dir")
+                .long("save-dir")
+                .value_name("DIR")
+                .help("Directory in which clients save and load worlds [default: saves]"),
+        )
+        .arg(
+            Arg::with_name("recording-dir")
+                .long("recording-dir")
+                .value_name("DIR")
+                .help("Directory in which clients start recordings [default: recordings]"),
+        )
+        .arg(
+            Arg::with_name("scenario-dir")
+                .long("scenario-dir")
+                .value_name("DIR")
+                .help("Directory from which clients load scenarios [default: scenarios]"),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .default_value("info")
+                .possible_values(&["error", "warn", "info", "debug", "trace"])
+                .help("Most verbose level of log messages that are printed"),
+        )
+        .arg(env(
+            Arg::with_name("log-filter")
+                .long("log-filter")
+                .value_name("DIRECTIVES")
+                .validator(|filter| {
+                    EnvFilter::try_new(filter)
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .help(
+                    "Filter such as `info,abm_server::network=debug` that selects which spans \
+                     and events are logged. Overrides the log level",
+                ),
+            "RUST_LOG",
+        ))
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .default_value("text")
+                .possible_values(&["text", "json"])
+                .help("Format in which log messages are printed"),
+        )
+        .arg(
+            Arg::with_name("diagnostics")
+                .long("diagnostics")
+                .conflicts_with("replay")
+                .help(
+                    "Log the frame delta and entity count at the debug level and sampled \
+                     sheep positions at the trace level",
+                ),
+        )
+}
+
+/// Parses the value of an argument that has already been validated.
+fn parse<T: FromStr>(matches: &ArgMatches, name: &str) -> T {
+    matches
+        .value_of(name)
+        .and_then(|value| value.parse().ok())
+        .expect("Argument was not validated.")
+}
+
+fn is_valid<T: FromStr>(value: String) -> Result<(), String>
+where
+    T::Err: ToString,
+{
+    value
+        .parse::<T>()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+fn is_positive_integer(value: String) -> Result<(), String> {
+    match value.parse::<u64>() {
+        Ok(number) if number > 0 => Ok(()),
+        _ => Err(format!("expected a positive integer, not {}", value)),
+    }
+}
+
+fn is_positive_number(value: String) -> Result<(), String> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(()),
+        _ => Err(format!("expected a positive number, not {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{app, Config, Mode};
+
+    fn options(args: &[&str]) -> crate::simulation::Options {
+        let matches = app(false).get_matches_from_safe(args).unwrap();
+        match Config::from_matches(&matches).unwrap().mode {
+            Mode::Simulate(options) => *options,
+            _ => panic!("Expected simulation mode."),
+        }
+    }
+
+    #[test]
+    fn rates() {
+        let options = options(&["abm-server", "--tick-rate", "50", "--broadcast-rate", "10"]);
+        assert_eq!(options.frame_duration.as_millis(), 20);
+        assert_eq!(options.broadcast_interval, 5);
+    }
+
+    #[test]
+    fn log_filter_overrides_level() {
+        let config = |args: &[&str]| {
+            let matches = app(false).get_matches_from_safe(args).unwrap();
+            Config::from_matches(&matches).unwrap()
+        };
+        let filter = "warn,abm_server::network=trace";
+        assert_eq!(
+            config(&["abm-server", "--log-level", "debug"]).log_filter,
+            "DEBUG"
+        );
+        assert_eq!(
+            config(&["abm-server", "--log-level", "debug", "--log-filter", filter]).log_filter,
+            filter
+        );
+    }
+
+    #[test]
+    fn invalid_args_are_rejected() {
+        for args in &[
+            vec!["abm-server", "--bind", "localhost"],
+            vec!["abm-server", "--tick-rate", "0"],
+            vec!["abm-server", "--tick-rate", "10", "--broadcast-rate", "20"],
+            vec!["abm-server", "--replay", "a.jsonl", "--scenario", "b.toml"],
+            vec!["abm-server", "--log-filter", "abm_server=loudest"],
+            vec!["abm-server", "--trajectory-interval", "0"],
+            vec!["abm-server", "--sweep", "sweep.toml", "--seed", "1"],
+            vec!["abm-server", "--jobs", "2"],
+        ] {
+            let result = app(false)
+                .get_matches_from_safe(args)
+                .and_then(|matches| Config::from_matches(&matches).map(|_| ()));
+            assert!(result.is_err(), "{:?} was accepted", args);
+        }
+    }
+}
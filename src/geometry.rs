@@ -1,9 +1,33 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Serialize)]
 pub struct BoundingBox {
     pub x_min: f32,
     pub x_max: f32,
     pub y_min: f32,
     pub y_max: f32,
 }
+
+impl BoundingBox {
+    pub fn new(x_min: f32, x_max: f32, y_min: f32, y_max: f32) -> BoundingBox {
+        BoundingBox {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.x_max - self.x_min
+    }
+
+    pub fn height(&self) -> f32 {
+        self.y_max - self.y_min
+    }
+
+    /// Returns true if the point is strictly inside the bounding box.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x > self.x_min && x < self.x_max && y > self.y_min && y < self.y_max
+    }
+}
@@ -0,0 +1,73 @@
+use hyper::{Body, Response, StatusCode};
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+
+/// File that is served for a request for a directory.
+const INDEX: &str = "index.html";
+
+/// Serves the file at the request path relative to the directory. Paths that
+/// would leave the directory are not found.
+pub async fn serve(dir: &Path, request_path: &str) -> Response<Body> {
+    let mut path = match resolve(dir, request_path) {
+        Some(path) => path,
+        None => return super::error(StatusCode::NOT_FOUND, "Not found"),
+    };
+    if fs::metadata(&path).await.is_ok_and(|meta| meta.is_dir()) {
+        path.push(INDEX);
+    }
+
+    match fs::read(&path).await {
+        Ok(contents) => Response::builder()
+            .header("Content-Type", content_type(&path))
+            .body(Body::from(contents))
+            .expect("Response is valid."),
+        Err(_) => super::error(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+/// Gets the path of the file in the directory that the request path names.
+/// Returns `None` if the request path contains anything other than plain file
+/// and directory names.
+fn resolve(dir: &Path, request_path: &str) -> Option<PathBuf> {
+    let relative = Path::new(request_path.trim_start_matches('/'));
+    if relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        Some(dir.join(relative))
+    } else {
+        None
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("ico") => "image/x-icon",
+        Some("wasm") => "application/wasm",
+        Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve;
+    use std::path::Path;
+
+    #[test]
+    fn paths_outside_the_directory_are_rejected() {
+        let dir = Path::new("static");
+        assert_eq!(resolve(dir, "/js/app.js"), Some(dir.join("js/app.js")));
+        assert_eq!(resolve(dir, "/"), Some(dir.to_path_buf()));
+        assert_eq!(resolve(dir, "/../secret"), None);
+        assert_eq!(resolve(dir, "/js/../../secret"), None);
+        assert_eq!(resolve(dir, "//etc/passwd"), Some(dir.join("etc/passwd")));
+    }
+}
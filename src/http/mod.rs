@@ -0,0 +1,275 @@
+mod files;
+mod options;
+
+pub use options::Options;
+
+use crate::metrics::Metrics;
+use crate::network::{self, channel, ClientCommand, IncomingMessage, Query, Role};
+use crate::shutdown::Shutdown;
+use crate::simulation;
+use futures_channel::oneshot;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+    convert::Infallible,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::timeout;
+
+/// Maximum time to wait for a room's simulation to answer a query or handle a
+/// command.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// State shared by the handlers of every request.
+struct Context {
+    options: Options,
+    network: network::Options,
+    senders: Arc<Mutex<channel::SenderManager>>,
+    metrics: Arc<Metrics>,
+}
+
+/// Binds the HTTP server to the configured address. The returned future
+/// serves requests until the server shuts down.
+///
+/// Routes:
+///
+/// * `GET /health` reports that the server is up and how many clients are
+///   connected.
+/// * `GET /metrics` reports the server's metrics in the Prometheus text
+///   format.
+/// * `GET /api/scenarios` lists the scenarios that clients can load.
+/// * `GET /api/rooms/<room>/state` gets the frame number, scenario, model
+///   parameters and agents of a room.
+/// * `GET /api/rooms/<room>/params` gets the model parameters of a room.
+/// * `GET /api/rooms/<room>/profile` gets the time taken by each system of a
+///   room's simulation.
+/// * `GET /api/rooms/<room>/analytics` gets the statistics of a room's flock
+///   during the last frame.
+/// * `POST /api/rooms/<room>/<command>` sends a command to a room's
+///   simulation. The command is named in the path and the rest of its fields
+///   are given in a JSON body, such as `POST /api/rooms/default/step` with
+///   `{"frames": 10}`. Only controllers may send commands, and commands that
+///   the simulation rejects are answered with `400 Bad Request`.
+/// * Any other `GET` request is served from the static directory.
+///
+/// Requests under `/api` and to `/metrics` must carry a token if clients must
+/// present one to connect, since they reveal the names and state of rooms.
+pub fn serve(
+    options: Options,
+    network: network::Options,
+    senders: Arc<Mutex<channel::SenderManager>>,
+    metrics: Arc<Metrics>,
+    mut shutdown: Shutdown,
+) -> hyper::Result<impl Future<Output = hyper::Result<()>>> {
+    let addr = options.addr;
+    let context = Arc::new(Context {
+        options,
+        network,
+        senders,
+        metrics,
+    });
+    let make_service = make_service_fn(move |_| {
+        let context = context.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(context.clone(), req))) }
+    });
+    let server = Server::try_bind(&addr)?
+        .serve(make_service)
+        .with_graceful_shutdown(async move { shutdown.wait().await });
+    Ok(server)
+}
+
+async fn handle(context: Arc<Context>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path().to_string();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let res = match (req.method(), segments.as_slice()) {
+        (&Method::GET, ["health"]) => health(&context),
+        (_, ["api", ..]) | (_, ["metrics"]) if !context.network.auth.authenticate(&req) => {
+            let mut res = error(StatusCode::UNAUTHORIZED, "Missing or invalid access token");
+            res.headers_mut()
+                .insert("WWW-Authenticate", "Bearer".parse().unwrap());
+            res
+        }
+        (&Method::GET, ["metrics"]) => metrics(&context),
+        (&Method::GET, ["api", "scenarios"]) => scenarios(&context),
+        (&Method::GET, ["api", "rooms", room, "state"]) => {
+            query(&context, room, Query::State).await
+        }
+        (&Method::GET, ["api", "rooms", room, "params"]) => {
+            query(&context, room, Query::Params).await
+        }
+        (&Method::GET, ["api", "rooms", room, "profile"]) => {
+            query(&context, room, Query::Profile).await
+        }
+        (&Method::GET, ["api", "rooms", room, "analytics"]) => {
+            query(&context, room, Query::Analytics).await
+        }
+        (&Method::POST, ["api", "rooms", room, command]) => {
+            let room = room.to_string();
+            let command = command.to_string();
+            request(&context, room, command, req).await
+        }
+        (_, ["api", ..]) => error(StatusCode::NOT_FOUND, "Not found"),
+        (&Method::GET, _) => match &context.options.static_dir {
+            Some(dir) => files::serve(dir, &path).await,
+            None => error(StatusCode::NOT_FOUND, "Not found"),
+        },
+        _ => error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+    };
+    Ok(res)
+}
+
+fn health(context: &Context) -> Response<Body> {
+    let clients = context.senders.lock().unwrap().client_addrs().count();
+    json(
+        StatusCode::OK,
+        serde_json::json!({ "status": "ok", "clients": clients }).to_string(),
+    )
+}
+
+fn metrics(context: &Context) -> Response<Body> {
+    let queue_depths = context.senders.lock().unwrap().queue_depths();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(Body::from(context.metrics.render(&queue_depths)))
+        .expect("Response is valid.")
+}
+
+fn scenarios(context: &Context) -> Response<Body> {
+    match simulation::scenario_names(&context.options.scenario_dir) {
+        Ok(names) => json(
+            StatusCode::OK,
+            serde_json::to_string(&names).expect("Names are serializable."),
+        ),
+        Err(err) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Unable to list scenarios: {}", err),
+        ),
+    }
+}
+
+/// Asks the simulation of the room for information about its world.
+async fn query(context: &Context, room: &str, query: Query) -> Response<Body> {
+    let (reply, response) = oneshot::channel();
+    context
+        .senders
+        .lock()
+        .unwrap()
+        .send_to_sim(IncomingMessage::Query {
+            room: room.to_string(),
+            query,
+            reply,
+        });
+    match timeout(QUERY_TIMEOUT, response).await {
+        Ok(Ok(body)) => json(StatusCode::OK, body),
+        Ok(Err(_)) => error(StatusCode::NOT_FOUND, "Room is not open"),
+        Err(_) => error(
+            StatusCode::GATEWAY_TIMEOUT,
+            "The simulation did not reply in time",
+        ),
+    }
+}
+
+/// Sends the command named in the request path to the simulation of the room
+/// and waits for the simulation to handle it. Commands that the simulation
+/// rejects are answered with the reason.
+async fn request(
+    context: &Context,
+    room: String,
+    command: String,
+    req: Request<Body>,
+) -> Response<Body> {
+    if Role::from_query(req.uri().query(), &context.network) != Role::Controller {
+        return error(
+            StatusCode::FORBIDDEN,
+            "Only controllers may send this command",
+        );
+    }
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(err) => return error(StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+    let command = match parse_command(&command, &body) {
+        Ok(ClientCommand::JoinRoom { .. }) => {
+            return error(StatusCode::BAD_REQUEST, "Only clients can join rooms")
+        }
+        Ok(ClientCommand::GetProfile) | Ok(ClientCommand::GetAnalytics) => {
+            return error(StatusCode::BAD_REQUEST, "Use GET to get reports")
+        }
+        Ok(command) => command,
+        Err(err) => return error(StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let (reply, response) = oneshot::channel();
+    context
+        .senders
+        .lock()
+        .unwrap()
+        .send_to_sim(IncomingMessage::Request {
+            room,
+            command,
+            reply,
+        });
+    match timeout(QUERY_TIMEOUT, response).await {
+        Ok(Ok(Ok(()))) => json(
+            StatusCode::ACCEPTED,
+            serde_json::json!({ "status": "accepted" }).to_string(),
+        ),
+        Ok(Ok(Err(reason))) => error(StatusCode::BAD_REQUEST, &reason),
+        Ok(Err(_)) => error(StatusCode::NOT_FOUND, "Room is not open"),
+        Err(_) => error(
+            StatusCode::GATEWAY_TIMEOUT,
+            "The simulation did not reply in time",
+        ),
+    }
+}
+
+/// Parses a command with the given type from a JSON object with the rest of
+/// its fields. An empty body is treated as an empty object.
+fn parse_command(command: &str, body: &[u8]) -> serde_json::Result<ClientCommand> {
+    let mut fields: serde_json::Map<String, serde_json::Value> = if body.is_empty() {
+        serde_json::Map::new()
+    } else {
+        serde_json::from_slice(body)?
+    };
+    fields.insert("type".to_string(), command.into());
+    serde_json::from_value(fields.into())
+}
+
+fn json(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .expect("Response is valid.")
+}
+
+/// Creates a response with a JSON body of the form `{"error": "<message>"}`.
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, serde_json::json!({ "error": message }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_command;
+    use crate::network::ClientCommand;
+
+    #[test]
+    fn commands_are_parsed_from_the_path_and_body() {
+        assert!(matches!(
+            parse_command("pause", b""),
+            Ok(ClientCommand::Pause)
+        ));
+        assert!(matches!(
+            parse_command("step", br#"{"frames": 10}"#),
+            Ok(ClientCommand::Step { frames: 10 })
+        ));
+        assert!(parse_command("step", b"").is_err());
+        assert!(parse_command("explode", b"").is_err());
+        assert!(parse_command("pause", b"[]").is_err());
+    }
+}
@@ -0,0 +1,15 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+/// Configuration of the HTTP server.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Address on which to listen for HTTP requests.
+    pub addr: SocketAddr,
+
+    /// Directory from which static files, such as a browser viewer, are
+    /// served. No files are served if this is `None`.
+    pub static_dir: Option<PathBuf>,
+
+    /// Directory whose scenarios are listed.
+    pub scenario_dir: PathBuf,
+}
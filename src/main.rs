@@ -1,48 +1,240 @@
 //! To run:
 //!
-//!     cargo run 127.0.0.1:12345
+//!     cargo run -- --bind 127.0.0.1:12345
+//!
+//! To resume a simulation from a save file run:
+//!
+//!     cargo run -- --load saves/example.json
+//!
+//! To initialize the simulation from a scenario file run:
+//!
+//!     cargo run -- --scenario scenarios/example.toml
+//!
+//! To stream a recording to clients instead of running the simulation run:
+//!
+//!     cargo run -- --replay recordings/example.jsonl
+//!
+//! To simulate 1000 frames as quickly as possible without any clients and
+//! record the result run:
+//!
+//!     cargo run -- --headless --frames 1000 --record recordings/batch.jsonl
+//!
+//! Add `--analytics analytics/batch.csv` to also write the polarization,
+//! milling, nearest neighbor distance, clusters and behaviors of the flock
+//! each frame. Clients can ask for the latest statistics with
+//! `{"type": "get_analytics"}`.
+//!
+//! Add `--trajectories trajectories/batch.csv --trajectory-interval 10` to
+//! write the position, heading, velocity and behavior of every agent every 10
+//! frames.
+//!
+//! To run a scenario across a grid of model parameters and seeds, write a
+//! sweep file such as:
+//!
+//!     scenario = "scenarios/example.toml"
+//!     frames = 3000
+//!     burn_in = 500
+//!     seeds = [1, 2, 3]
+//!
+//!     [params]
+//!     noise = [0.2, 0.4, 0.8]
+//!     walking_speed = [0.15, 0.3]
+//!
+//! and run `cargo run --release -- --sweep sweep.toml --jobs 8`. A row of
+//! flock statistics for each run is written to `sweeps/summary.csv`. Add
+//! `full_outputs = true` to the sweep to also write the analytics and
+//! trajectories of each run to `sweeps/run-<N>/`.
+//!
+//! To accept `wss://` connections with a locally generated self-signed
+//! certificate run:
+//!
+//!     openssl req -x509 -newkey rsa:2048 -nodes -days 365 -subj /CN=localhost \
+//!         -keyout key.pem -out cert.pem
+//!     cargo run -- --tls-cert cert.pem --tls-key key.pem
+//!
+//! To also serve the HTTP API and a browser viewer from the `static`
+//! directory run:
+//!
+//!     cargo run -- --http-bind 127.0.0.1:8081 --static-dir static
+//!
+//! Prometheus can then scrape the server's metrics from
+//! `http://127.0.0.1:8081/metrics`, presenting a bearer token if clients must
+//! present one to connect.
+//!
+//! To log the frame delta, entity count and a few sheep positions each frame,
+//! along with the span of each frame and system, run:
+//!
+//!     RUST_LOG=info,abm_server::simulation=trace cargo run -- --diagnostics
+//!
+//! Run `cargo run -- --help` for the full list of options.
 //!
 //! And then in another window run:
 //!
 //!     cargo run ws://127.0.0.1:12345/
 //!
 //! You can run the second command in multiple windows and then chat between the
-//! two, seeing the messages from the other client as they're received. For all
-//! connected clients they'll all join the same room and see everyone else's
-//! messages.
+//! two, seeing the messages from the other client as they're received. Clients
+//! connecting to the root path join the default room. Clients connecting to
+//! another path, such as `ws://127.0.0.1:12345/arena`, join the room with that
+//! name, which runs a separate simulation.
+//!
+//! Clients connecting with `?compression=deflate` receive binary frames
+//! instead of text frames. The first byte of each frame is 1 if the rest of
+//! the frame is zlib compressed JSON and 0 if it is plain JSON.
 
+mod config;
 mod geometry;
+mod http;
+mod metrics;
 mod network;
+mod shutdown;
 mod simulation;
+#[cfg(test)]
+mod test_util;
 
+use config::{Config, Mode};
 use futures_util::{future, pin_mut};
 use network::channel;
 use std::{
-    env,
     io::Error as IoError,
+    process,
     sync::{Arc, Mutex},
 };
 use tokio::net::TcpListener;
+use tracing::{error, info};
 
 #[tokio::main]
 async fn main() -> Result<(), IoError> {
-    let addr = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let config = Config::from_args();
+    let subscriber = tracing_subscriber::fmt().with_env_filter(config.log_filter.as_str());
+    if config.log_json {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
+
+    // Sweeps run to completion on threads of their own.
+    if let Mode::Sweep {
+        file,
+        output_dir,
+        jobs,
+        options,
+    } = &config.mode
+    {
+        if let Err(err) = simulation::sweep(file, output_dir, *jobs, options) {
+            error!("{}", err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
+    // Shut down when the process is interrupted or terminated.
+    let (trigger, shutdown) = shutdown::channel();
+    tokio::spawn(async move {
+        match shutdown::signal_received().await {
+            Ok(()) => {
+                info!("Shutting down");
+                trigger.fire();
+            }
+            Err(err) => {
+                error!("Unable to listen for shutdown signals: {}", err);
+                // Dropping the trigger would shut the server down.
+                future::pending::<()>().await;
+            }
+        }
+    });
+
+    // Either replay a recording or run the simulation.
     let senders = Arc::new(Mutex::new(channel::SenderManager::new()));
+    let metrics = Arc::new(metrics::Metrics::default());
+    let simulation = match config.mode {
+        Mode::Replay(path) => {
+            future::Either::Right(simulation::replay(senders.clone(), path, shutdown.clone()))
+        }
+        Mode::Simulate(options) => future::Either::Left(simulation::run(
+            senders.clone(),
+            *options,
+            metrics.clone(),
+            shutdown.clone(),
+        )),
+        Mode::Sweep { .. } => unreachable!("Sweeps are run before the server starts."),
+    };
+
+    if config.headless {
+        if let Err(err) = simulation.await {
+            error!("{}", err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Read the TLS identity before listening so that a bad certificate is
+    // reported right away.
+    let tls = match config.network.tls.as_ref().map(network::TlsFiles::acceptor) {
+        Some(Ok(acceptor)) => Some(acceptor),
+        Some(Err(err)) => {
+            error!("Unable to load TLS certificate: {}", err);
+            process::exit(1);
+        }
+        None => None,
+    };
 
     // Create the event loop and TCP listener we'll accept connections on.
-    let try_socket = TcpListener::bind(&addr).await;
-    // TODO: Include error message: "Failed to bind."
-    let mut listener = try_socket?;
-    println!("Listening on: {}", addr);
-
-    // Run the connection handlers and simulation asynchronously.
-    let handlers = network::accept_connections(&mut listener, senders.clone());
-    let simulation = simulation::run(senders.clone());
+    let mut listener = match TcpListener::bind(&config.addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Unable to bind to {}: {}", config.addr, err);
+            process::exit(1);
+        }
+    };
+    let scheme = if tls.is_some() { "wss" } else { "ws" };
+    info!("Listening on: {}://{}", scheme, config.addr);
+
+    if let Some(options) = config.http {
+        let addr = options.addr;
+        let network = config.network.clone();
+        match http::serve(
+            options,
+            network,
+            senders.clone(),
+            metrics.clone(),
+            shutdown.clone(),
+        ) {
+            Ok(server) => {
+                info!("Serving HTTP on: http://{}", addr);
+                tokio::spawn(async move {
+                    if let Err(err) = server.await {
+                        error!("HTTP server failed: {}", err);
+                    }
+                });
+            }
+            Err(err) => {
+                error!("Unable to bind to {}: {}", addr, err);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Run the connection handlers and simulation asynchronously. Once they
+    // stop, close the connections of the remaining clients.
+    let handlers = network::accept_connections(
+        &mut listener,
+        senders.clone(),
+        config.network,
+        tls,
+        metrics,
+        shutdown,
+    );
     pin_mut!(handlers, simulation);
-    future::select(handlers, simulation).await;
+    let result = match future::select(handlers, simulation).await {
+        future::Either::Left(((), simulation)) => simulation.await,
+        future::Either::Right((result, _)) => result,
+    };
+    network::close_connections(senders, shutdown::REASON).await;
+    if let Err(err) = result {
+        error!("{}", err);
+        process::exit(1);
+    }
 
     Ok(())
 }
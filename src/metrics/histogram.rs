@@ -0,0 +1,73 @@
+use std::{fmt::Write, time::Duration};
+
+/// Upper bounds in seconds of the histogram buckets. They cover durations from
+/// a fraction of a millisecond, typical of a single system, up to several
+/// frames.
+const BUCKETS: [f64; 12] = [
+    0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25,
+];
+
+/// Distribution of observed durations.
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    /// Number of observations less than or equal to each bucket's bound.
+    buckets: [u64; BUCKETS.len()],
+
+    count: u64,
+
+    /// Sum of the observations in seconds.
+    sum: f64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bound, count) in BUCKETS.iter().zip(self.buckets.iter_mut()) {
+            if secs <= *bound {
+                *count += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+
+    /// Writes the histogram's samples in the Prometheus text format. The labels
+    /// are written inside the braces of each sample, as in `room="default"`.
+    pub fn write(&self, out: &mut String, name: &str, labels: &str) {
+        for (bound, count) in BUCKETS.iter().zip(self.buckets.iter()) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Histogram;
+    use std::time::Duration;
+
+    #[test]
+    fn buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(Duration::from_micros(200));
+        histogram.observe(Duration::from_millis(20));
+
+        let mut out = String::new();
+        histogram.write(&mut out, "abm_test_seconds", "room=\"a\"");
+        assert!(out.contains("abm_test_seconds_bucket{room=\"a\",le=\"0.0001\"} 0\n"));
+        assert!(out.contains("abm_test_seconds_bucket{room=\"a\",le=\"0.00025\"} 1\n"));
+        assert!(out.contains("abm_test_seconds_bucket{room=\"a\",le=\"0.025\"} 2\n"));
+        assert!(out.contains("abm_test_seconds_bucket{room=\"a\",le=\"+Inf\"} 2\n"));
+        assert!(out.contains("abm_test_seconds_count{room=\"a\"} 2\n"));
+    }
+}
@@ -0,0 +1,251 @@
+mod histogram;
+
+pub use histogram::Histogram;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Measurements of the server's performance that are exposed to Prometheus.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Number of bytes sent to clients in WebSocket messages.
+    sent_bytes: AtomicU64,
+
+    rooms: Mutex<BTreeMap<String, RoomMetrics>>,
+}
+
+/// Measurements of a room's simulation.
+#[derive(Debug, Default)]
+pub struct RoomMetrics {
+    pub frame_duration: Histogram,
+    pub system_durations: BTreeMap<&'static str, Histogram>,
+
+    /// Number of frames that were skipped because the simulation fell behind
+    /// real time.
+    pub dropped_frames: u64,
+
+    /// Time that a frame may take without delaying the next one, and the
+    /// number of frames that took longer.
+    pub frame_budget: Duration,
+    pub frame_overruns: u64,
+
+    /// Number of entities of each kind, and of each behavior for kinds that
+    /// have one, such as `("sheep", Some("walking"))`.
+    pub entities: BTreeMap<(&'static str, Option<&'static str>), usize>,
+
+    /// Number of messages in the inbox during the last frame.
+    pub inbox_len: usize,
+
+    /// Number of messages in the outbox during the last frame.
+    pub outbox_len: usize,
+}
+
+impl Metrics {
+    pub fn add_sent_bytes(&self, count: usize) {
+        self.sent_bytes.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Updates the measurements of the room.
+    pub fn update_room<F>(&self, room: &str, update: F)
+    where
+        F: FnOnce(&mut RoomMetrics),
+    {
+        let mut rooms = self.rooms.lock().unwrap();
+        if !rooms.contains_key(room) {
+            rooms.insert(room.to_string(), RoomMetrics::default());
+        }
+        update(rooms.get_mut(room).expect("Room was inserted."));
+    }
+
+    /// Discards the measurements of a room that was closed.
+    pub fn remove_room(&self, room: &str) {
+        self.rooms.lock().unwrap().remove(room);
+    }
+
+    /// Renders the metrics in the Prometheus text format. Client metrics are
+    /// measured when they're rendered, so the queue depths of the connected
+    /// clients are given. Clients aren't labeled individually, so that the
+    /// number of series stays bounded and client addresses aren't exposed.
+    pub fn render(&self, queue_depths: &[usize]) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "abm_connected_clients",
+            "gauge",
+            "Number of connected WebSocket clients.",
+        );
+        let _ = writeln!(out, "abm_connected_clients {}", queue_depths.len());
+
+        header(
+            &mut out,
+            "abm_queued_messages",
+            "gauge",
+            "Number of messages waiting to be sent to clients.",
+        );
+        let _ = writeln!(
+            out,
+            "abm_queued_messages {}",
+            queue_depths.iter().sum::<usize>()
+        );
+
+        header(
+            &mut out,
+            "abm_client_queue_depth_max",
+            "gauge",
+            "Largest number of messages waiting to be sent to a single client.",
+        );
+        let _ = writeln!(
+            out,
+            "abm_client_queue_depth_max {}",
+            queue_depths.iter().max().copied().unwrap_or(0)
+        );
+
+        header(
+            &mut out,
+            "abm_sent_bytes_total",
+            "counter",
+            "Bytes sent to clients in WebSocket messages.",
+        );
+        let _ = writeln!(
+            out,
+            "abm_sent_bytes_total {}",
+            self.sent_bytes.load(Ordering::Relaxed)
+        );
+
+        let rooms = self.rooms.lock().unwrap();
+        header(
+            &mut out,
+            "abm_frame_duration_seconds",
+            "histogram",
+            "Time taken to run the systems of a frame.",
+        );
+        for (room, metrics) in rooms.iter() {
+            let labels = format!("room=\"{}\"", room);
+            metrics
+                .frame_duration
+                .write(&mut out, "abm_frame_duration_seconds", &labels);
+        }
+
+        header(
+            &mut out,
+            "abm_system_duration_seconds",
+            "histogram",
+            "Time taken by a system during a frame.",
+        );
+        for (room, metrics) in rooms.iter() {
+            for (system, histogram) in &metrics.system_durations {
+                let labels = format!("room=\"{}\",system=\"{}\"", room, system);
+                histogram.write(&mut out, "abm_system_duration_seconds", &labels);
+            }
+        }
+
+        header(
+            &mut out,
+            "abm_dropped_frames_total",
+            "counter",
+            "Frames skipped because the simulation fell behind real time.",
+        );
+        for (room, metrics) in rooms.iter() {
+            let _ = writeln!(
+                out,
+                "abm_dropped_frames_total{{room=\"{}\"}} {}",
+                room, metrics.dropped_frames
+            );
+        }
+
+        header(
+            &mut out,
+            "abm_frame_budget_seconds",
+            "gauge",
+            "Time that a frame may take without delaying the next one.",
+        );
+        for (room, metrics) in rooms.iter() {
+            let _ = writeln!(
+                out,
+                "abm_frame_budget_seconds{{room=\"{}\"}} {}",
+                room,
+                metrics.frame_budget.as_secs_f64()
+            );
+        }
+
+        header(
+            &mut out,
+            "abm_frame_overruns_total",
+            "counter",
+            "Frames that took longer than their budget.",
+        );
+        for (room, metrics) in rooms.iter() {
+            let _ = writeln!(
+                out,
+                "abm_frame_overruns_total{{room=\"{}\"}} {}",
+                room, metrics.frame_overruns
+            );
+        }
+
+        header(
+            &mut out,
+            "abm_entities",
+            "gauge",
+            "Number of entities by kind and behavior.",
+        );
+        for (room, metrics) in rooms.iter() {
+            for ((kind, behavior), count) in &metrics.entities {
+                let _ = match behavior {
+                    Some(behavior) => writeln!(
+                        out,
+                        "abm_entities{{room=\"{}\",kind=\"{}\",behavior=\"{}\"}} {}",
+                        room, kind, behavior, count
+                    ),
+                    None => writeln!(
+                        out,
+                        "abm_entities{{room=\"{}\",kind=\"{}\"}} {}",
+                        room, kind, count
+                    ),
+                };
+            }
+        }
+
+        header(
+            &mut out,
+            "abm_inbox_messages",
+            "gauge",
+            "Number of messages handled by the simulation during the last frame.",
+        );
+        for (room, metrics) in rooms.iter() {
+            let _ = writeln!(
+                out,
+                "abm_inbox_messages{{room=\"{}\"}} {}",
+                room, metrics.inbox_len
+            );
+        }
+
+        header(
+            &mut out,
+            "abm_outbox_messages",
+            "gauge",
+            "Number of messages sent by the simulation during the last frame.",
+        );
+        for (room, metrics) in rooms.iter() {
+            let _ = writeln!(
+                out,
+                "abm_outbox_messages{{room=\"{}\"}} {}",
+                room, metrics.outbox_len
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
@@ -0,0 +1,135 @@
+use super::query_param;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{
+    borrow::Cow,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use subtle::ConstantTimeEq;
+use tungstenite::http::Request;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Credentials that clients must present when they connect. A client presents
+/// a token with an `Authorization: Bearer <token>` header or, since browsers
+/// can't set headers on WebSocket requests, with an `access_token` query
+/// parameter.
+///
+/// A token is accepted if it is one of the configured tokens or if it is
+/// signed with the configured secret. Signed tokens have the form
+/// `<name>.<expires>.<signature>`, where `expires` is a Unix timestamp in
+/// seconds and `signature` is the hex encoded HMAC-SHA256 of
+/// `<name>.<expires>`.
+#[derive(Clone, Debug, Default)]
+pub struct Auth {
+    pub tokens: Vec<String>,
+    pub secret: Option<Vec<u8>>,
+}
+
+impl Auth {
+    /// Returns true if clients must present a token.
+    pub fn is_required(&self) -> bool {
+        !self.tokens.is_empty() || self.secret.is_some()
+    }
+
+    /// Returns true if the upgrade or HTTP request carries an accepted token
+    /// or no token is required.
+    pub fn authenticate<B>(&self, req: &Request<B>) -> bool {
+        if !self.is_required() {
+            return true;
+        }
+        let header_token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(Cow::Borrowed);
+        let query_token = query_param(req.uri().query(), "access_token");
+        header_token
+            .or(query_token)
+            .is_some_and(|token| self.accepts(&token))
+    }
+
+    /// Returns true if the token is one of the configured tokens or a signed
+    /// token that has not expired.
+    pub fn accepts(&self, token: &str) -> bool {
+        is_listed(&self.tokens, token) || self.accepts_signed(token)
+    }
+
+    fn accepts_signed(&self, token: &str) -> bool {
+        let secret = match &self.secret {
+            Some(secret) => secret,
+            None => return false,
+        };
+        let (claims, signature) = match token.rsplit_once('.') {
+            Some(parts) => parts,
+            None => return false,
+        };
+        let expires = claims
+            .rsplit_once('.')
+            .and_then(|(_, expires)| expires.parse::<u64>().ok());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        if expires.is_none_or(|expires| now >= expires) {
+            return false;
+        }
+
+        let signature = match hex::decode(signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length.");
+        mac.update(claims.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+/// Returns true if the token is one of the listed tokens. Every token is
+/// compared in constant time so that the time taken doesn't reveal how much
+/// of a token was guessed.
+pub fn is_listed(tokens: &[String], token: &str) -> bool {
+    tokens.iter().fold(false, |listed, t| {
+        listed | bool::from(t.as_bytes().ct_eq(token.as_bytes()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Auth, HmacSha256};
+    use hmac::Mac;
+    use tungstenite::http::Request;
+
+    fn sign(secret: &[u8], claims: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+        mac.update(claims.as_bytes());
+        format!("{}.{}", claims, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn accepts_listed_and_signed_tokens() {
+        let auth = Auth {
+            tokens: vec!["listed".to_string()],
+            secret: Some(b"secret".to_vec()),
+        };
+        assert!(auth.accepts("listed"));
+        assert!(auth.accepts(&sign(b"secret", "alice.99999999999")));
+
+        assert!(!auth.accepts("unlisted"));
+        assert!(!auth.accepts(&sign(b"other", "alice.99999999999")));
+        assert!(!auth.accepts(&sign(b"secret", "alice.1")));
+        assert!(!auth.accepts(&sign(b"secret", "alice")));
+    }
+
+    #[test]
+    fn query_tokens_are_percent_decoded() {
+        let auth = Auth {
+            tokens: vec!["a+b/c=".to_string()],
+            secret: None,
+        };
+        let request = |uri| Request::builder().uri(uri).body(()).unwrap();
+        assert!(auth.authenticate(&request("/?access_token=a%2Bb%2Fc%3D")));
+        assert!(auth.authenticate(&request("/?access_token=a+b/c=")));
+        assert!(!auth.authenticate(&request("/?access_token=a%2Bb")));
+    }
+}
@@ -1,42 +1,95 @@
-use super::message::{IncomingMessage, OutgoingMessage};
+use super::error::NetworkError;
+use super::message::{Broadcast, IncomingMessage, OutgoingMessage, SharedMessage};
+use super::queue::ClientQueue;
 use futures_channel::mpsc::UnboundedSender;
-use std::{collections::HashMap, net::SocketAddr};
+use std::{collections::HashMap, convert::TryFrom, net::SocketAddr, sync::Arc};
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame, Message};
+
+/// A message on a client's queue.
+pub enum ClientMessage {
+    /// A message addressed to this client alone.
+    Outgoing(OutgoingMessage),
+
+    /// A world state shared with other clients.
+    WorldState(SharedMessage),
+
+    /// Checks that the client is still alive.
+    Ping,
+
+    /// Closes the connection for the given reason.
+    Close(String),
+}
+
+impl TryFrom<ClientMessage> for Message {
+    type Error = NetworkError;
+
+    fn try_from(msg: ClientMessage) -> Result<Self, Self::Error> {
+        match msg {
+            ClientMessage::Outgoing(msg) => Message::try_from(msg),
+            ClientMessage::WorldState(msg) => Ok(Message::text(msg.json())),
+            ClientMessage::Ping => Ok(Message::Ping(vec![])),
+            ClientMessage::Close(reason) => Ok(Message::Close(Some(CloseFrame {
+                code: CloseCode::Away,
+                reason: reason.into(),
+            }))),
+        }
+    }
+}
 
 /// Contains the sender end of a channel for messages to the simulation and the
-/// sender ends of channels for messages to connected clients.
+/// queues of messages to connected clients.
 pub struct SenderManager {
     /// The sender end of a channel that the server will listen to for incoming
     /// messages. Messages sent on this channel will be sent to the simulation.
     sim_sender: Option<UnboundedSender<IncomingMessage>>,
 
-    /// Contains the sender end of a channel for each client connected to the
-    /// server. Messages sent on one of these channels will be forwarded to the
-    /// connection on the respective socket.
-    client_senders: HashMap<SocketAddr, UnboundedSender<OutgoingMessage>>,
+    /// Contains a queue for each client connected to the server. Messages
+    /// pushed onto one of these queues will be forwarded to the connection on
+    /// the respective socket.
+    client_queues: HashMap<SocketAddr, Arc<ClientQueue>>,
+
+    /// Identifier assigned to the next client that connects.
+    next_client_id: u64,
 }
 
 impl SenderManager {
     pub fn new() -> SenderManager {
         SenderManager {
             sim_sender: None,
-            client_senders: HashMap::new(),
+            client_queues: HashMap::new(),
+            next_client_id: 1,
         }
     }
 
-    pub fn insert_client_sender(
-        &mut self,
-        addr: SocketAddr,
-        sender: UnboundedSender<OutgoingMessage>,
-    ) {
-        self.client_senders.insert(addr, sender);
+    /// Inserts the queue of a newly connected client and returns the
+    /// identifier assigned to the client.
+    pub fn insert_client_queue(&mut self, addr: SocketAddr, queue: Arc<ClientQueue>) -> u64 {
+        self.client_queues.insert(addr, queue);
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+        client_id
     }
 
     pub fn insert_sim_sender(&mut self, sender: UnboundedSender<IncomingMessage>) {
         self.sim_sender = Some(sender);
     }
 
-    pub fn remove_client_sender(&mut self, addr: &SocketAddr) {
-        self.client_senders.remove(&addr);
+    pub fn remove_client_queue(&mut self, addr: &SocketAddr) {
+        self.client_queues.remove(addr);
+    }
+
+    /// Gets the addresses of all connected clients.
+    pub fn client_addrs(&self) -> impl Iterator<Item = &SocketAddr> {
+        self.client_queues.keys()
+    }
+
+    /// Gets the number of messages waiting to be sent to each connected
+    /// client.
+    pub fn queue_depths(&self) -> Vec<usize> {
+        self.client_queues
+            .values()
+            .map(|queue| queue.len())
+            .collect()
     }
 
     /// Attempts to send a message on the simulation's channel.
@@ -46,10 +99,28 @@ impl SenderManager {
         }
     }
 
-    /// Attempts to send a message on the client's channel.
+    /// Pushes a message onto the client's queue if the client is connected.
     pub fn send_to_client(&self, msg: OutgoingMessage) {
-        if let Some(sender) = &self.client_senders.get(&msg.recipient) {
-            let _ = sender.unbounded_send(msg);
+        if let Some(queue) = self.client_queues.get(&msg.recipient) {
+            queue.push(ClientMessage::Outgoing(msg));
+        }
+    }
+
+    /// Pushes a shared message onto the queue of each connected recipient.
+    pub fn broadcast(&self, broadcast: Broadcast) {
+        for addr in &broadcast.recipients {
+            if let Some(queue) = self.client_queues.get(addr) {
+                queue.push(ClientMessage::WorldState(broadcast.message.clone()));
+            }
+        }
+    }
+
+    /// Closes every client's queue with a close message with the reason. The
+    /// connections are closed once the messages already on the queues have
+    /// been sent.
+    pub fn close_clients(&self, reason: &str) {
+        for queue in self.client_queues.values() {
+            queue.close(reason);
         }
     }
 }
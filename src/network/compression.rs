@@ -0,0 +1,74 @@
+use super::error::NetworkResult;
+use flate2::{write::ZlibEncoder, Compression};
+use std::io::{self, Write};
+use tungstenite::protocol::Message;
+
+/// Flag at the start of a binary frame whose remaining bytes are JSON.
+pub const UNCOMPRESSED: u8 = 0;
+
+/// Flag at the start of a binary frame whose remaining bytes are zlib
+/// compressed JSON. Browsers can decompress them with
+/// `new DecompressionStream("deflate")`.
+pub const DEFLATE: u8 = 1;
+
+/// Messages shorter than this are not worth compressing.
+const MIN_COMPRESSED_LEN: usize = 256;
+
+/// Converts a text message into a binary message that starts with a flag
+/// saying whether the rest of the message is compressed. Other messages are
+/// returned unchanged.
+pub fn compress(msg: Message) -> NetworkResult<Message> {
+    match msg {
+        Message::Text(text) => Ok(Message::Binary(compress_text(&text)?)),
+        msg => Ok(msg),
+    }
+}
+
+/// Gets the contents of the binary message that [`compress`] would convert
+/// the text into.
+pub fn compress_text(text: &str) -> io::Result<Vec<u8>> {
+    if text.len() < MIN_COMPRESSED_LEN {
+        let mut data = Vec::with_capacity(text.len() + 1);
+        data.push(UNCOMPRESSED);
+        data.extend_from_slice(text.as_bytes());
+        return Ok(data);
+    }
+
+    let mut encoder = ZlibEncoder::new(vec![DEFLATE], Compression::fast());
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, DEFLATE, UNCOMPRESSED};
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+    use tungstenite::protocol::Message;
+
+    fn binary(msg: Message) -> Vec<u8> {
+        match compress(msg).unwrap() {
+            Message::Binary(data) => data,
+            _ => panic!("Expected a binary message."),
+        }
+    }
+
+    #[test]
+    fn short_messages_are_not_compressed() {
+        assert_eq!(binary(Message::text("{}")), vec![UNCOMPRESSED, b'{', b'}']);
+    }
+
+    #[test]
+    fn long_messages_are_compressed() {
+        let text = format!("[{}0]", "0,".repeat(1000));
+        let data = binary(Message::text(text.clone()));
+        assert_eq!(data[0], DEFLATE);
+        assert!(data.len() < text.len() / 10);
+
+        let mut decompressed = String::new();
+        ZlibDecoder::new(&data[1..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, text);
+    }
+}
@@ -1,12 +1,12 @@
-use serde_json;
-use std::{convert::From, fmt};
-use tungstenite;
+use std::{convert::From, fmt, io};
 
 pub type NetworkResult<T> = Result<T, NetworkError>;
 
 #[derive(Debug)]
 pub enum NetworkError {
+    Io(io::Error),
     Serde(serde_json::Error),
+    Tls(native_tls::Error),
     Tungstenite(tungstenite::error::Error),
 }
 
@@ -14,12 +14,26 @@ impl fmt::Display for NetworkError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             // Use the underlying implementations of `Display`.
+            NetworkError::Io(ref err) => write!(f, "IO error: {}", err),
             NetworkError::Serde(ref err) => write!(f, "Serde error: {}", err),
+            NetworkError::Tls(ref err) => write!(f, "TLS error: {}", err),
             NetworkError::Tungstenite(ref err) => write!(f, "Tungstenite error: {}", err),
         }
     }
 }
 
+impl From<io::Error> for NetworkError {
+    fn from(err: io::Error) -> NetworkError {
+        NetworkError::Io(err)
+    }
+}
+
+impl From<native_tls::Error> for NetworkError {
+    fn from(err: native_tls::Error) -> NetworkError {
+        NetworkError::Tls(err)
+    }
+}
+
 impl From<serde_json::Error> for NetworkError {
     fn from(err: serde_json::Error) -> NetworkError {
         NetworkError::Serde(err)
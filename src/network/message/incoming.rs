@@ -1,16 +1,189 @@
+use crate::geometry::BoundingBox;
 use crate::network::error::NetworkResult;
+use crate::network::role::Role;
+use crate::simulation::SpawnGroup;
+use futures_channel::oneshot;
 use serde::Deserialize;
+use serde_json::{Map, Value};
 use std::net::SocketAddr;
 use tungstenite::protocol::Message;
 
-/// Message sent to the server.
+/// Event from a client connection, or a request from the HTTP API, that is
+/// forwarded to the simulation.
+#[derive(Debug)]
+pub enum IncomingMessage {
+    /// The client's WebSocket connection was established or the client
+    /// joined another room.
+    Connected {
+        sender: SocketAddr,
+        client_id: u64,
+
+        /// Name of the room that the client is in.
+        room: String,
+
+        role: Role,
+    },
+
+    /// The client's connection was closed.
+    Disconnected(SocketAddr),
+
+    /// Message sent to the server by the client.
+    Message {
+        sender: SocketAddr,
+
+        /// The command contained in the message, or `None` if the message is
+        /// not a recognized command.
+        command: Option<ClientCommand>,
+    },
+
+    /// Request for information about the world in a room. The simulation
+    /// replies with JSON at the end of the next frame.
+    Query {
+        room: String,
+        query: Query,
+        reply: oneshot::Sender<String>,
+    },
+
+    /// Command sent to a room through the HTTP API. The simulation replies
+    /// once the command has been handled, or with the reason that it was
+    /// rejected.
+    Request {
+        room: String,
+        command: ClientCommand,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+}
+
+/// Information about a room's world that can be queried.
+#[derive(Clone, Copy, Debug)]
+pub enum Query {
+    /// The frame number, scenario, model parameters and the state of every
+    /// agent.
+    State,
+
+    /// The model parameters.
+    Params,
+
+    /// The time taken by each system of the simulation.
+    Profile,
+
+    /// The statistics of the flock during the last frame.
+    Analytics,
+}
+
+/// Commands that a client can send to the simulation. Commands are JSON objects
+/// with a `type` field naming the command, such as
+/// `{"type": "save_world", "name": "example"}`.
 #[derive(Deserialize, Debug)]
-pub struct IncomingMessage {
-    pub sender: SocketAddr,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// Save the world to the save file with the given name.
+    SaveWorld { name: String },
+
+    /// Replace the world with the contents of the save file with the given
+    /// name.
+    LoadWorld { name: String },
+
+    /// Replace the world with the scenario with the given name.
+    LoadScenario { name: String },
+
+    /// Start recording every frame to the recording with the given name.
+    StartRecording { name: String },
+
+    /// Stop the recording in progress.
+    StopRecording,
+
+    /// Leave the current room and join the room with the given name, opening
+    /// the room if needed.
+    JoinRoom { name: String },
+
+    /// Create agents laid out like one of a scenario's spawn groups, as in
+    /// `{"type": "spawn", "kind": "uniform_random", "count": 10,
+    /// "behavior": "Walking"}`.
+    Spawn(SpawnGroup),
+
+    /// Delete the agents inside the area, or every agent if no area is given.
+    DeleteAgents { area: Option<BoundingBox> },
+
+    /// Change the model parameters named in the command, as in
+    /// `{"type": "set_params", "noise": 0.2}`. Other parameters keep their
+    /// values.
+    SetParams(Map<String, Value>),
+
+    /// Simulate the given number of frames and then pause.
+    Step { frames: u64 },
+
+    /// Pause the simulation or playback.
+    Pause,
+
+    /// Resume the simulation or playback.
+    Resume,
+
+    /// Reply with the time taken by each system of the simulation.
+    GetProfile,
+
+    /// Reply with the statistics of the flock during the last frame.
+    GetAnalytics,
+
+    /// Jump to the given frame. Only supported in replay mode.
+    Seek { frame: u64 },
+
+    /// Set the playback speed as a multiple of real time. Only supported in
+    /// replay mode.
+    SetSpeed { speed: f32 },
 }
 
 impl IncomingMessage {
-    pub fn try_new(sender: SocketAddr, _ws_msg: Message) -> NetworkResult<IncomingMessage> {
-        Ok(IncomingMessage { sender })
+    pub fn try_new(sender: SocketAddr, ws_msg: Message) -> NetworkResult<IncomingMessage> {
+        let command = match ws_msg {
+            Message::Text(text) => serde_json::from_str(&text).ok(),
+            _ => None,
+        };
+        Ok(IncomingMessage::Message { sender, command })
+    }
+
+    /// Gets the address of the client that the event came from, or `None` if
+    /// it didn't come from a client.
+    pub fn sender(&self) -> Option<SocketAddr> {
+        match *self {
+            IncomingMessage::Connected { sender, .. } => Some(sender),
+            IncomingMessage::Disconnected(sender) => Some(sender),
+            IncomingMessage::Message { sender, .. } => Some(sender),
+            IncomingMessage::Query { .. } | IncomingMessage::Request { .. } => None,
+        }
+    }
+
+    /// Gets the recognized command sent by the client or through the HTTP
+    /// API, if any.
+    pub fn command(&self) -> Option<&ClientCommand> {
+        match self {
+            IncomingMessage::Message { command, .. } => command.as_ref(),
+            IncomingMessage::Request { command, .. } => Some(command),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientCommand;
+
+    #[test]
+    fn nested_commands_are_parsed() {
+        let cmd: ClientCommand = serde_json::from_str(
+            r#"{"type": "spawn", "kind": "uniform_random", "count": 10, "behavior": "Walking"}"#,
+        )
+        .unwrap();
+        assert!(matches!(cmd, ClientCommand::Spawn(_)));
+
+        let cmd: ClientCommand =
+            serde_json::from_str(r#"{"type": "set_params", "noise": 0.2}"#).unwrap();
+        match cmd {
+            ClientCommand::SetParams(changes) => assert_eq!(changes["noise"], 0.2),
+            _ => panic!("Expected a set_params command."),
+        }
+
+        let cmd: ClientCommand = serde_json::from_str(r#"{"type": "delete_agents"}"#).unwrap();
+        assert!(matches!(cmd, ClientCommand::DeleteAgents { area: None }));
     }
 }
@@ -1,5 +1,8 @@
 mod incoming;
 mod outgoing;
 
-pub use incoming::IncomingMessage;
-pub use outgoing::OutgoingMessage;
+pub use incoming::{ClientCommand, IncomingMessage, Query};
+pub use outgoing::{
+    AgentState, Broadcast, FlockStats, FrameProfile, OutgoingMessage, ProfileReport, SharedMessage,
+    SystemProfile, Welcome, PROTOCOL_VERSION,
+};
@@ -1,35 +1,240 @@
-use crate::network::error::NetworkError;
-use serde::Serialize;
-use std::{convert::TryFrom, net::SocketAddr};
+use crate::geometry::BoundingBox;
+use crate::network::compression;
+use crate::network::error::{NetworkError, NetworkResult};
+use crate::network::role::Role;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    net::SocketAddr,
+    sync::{Arc, OnceLock},
+};
 use tungstenite::protocol::Message;
 
+/// Version of the protocol spoken with clients. Incremented whenever messages
+/// change in a way that would break existing clients.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// Message sent by the simulation server.
 #[derive(Serialize, Debug)]
 pub struct OutgoingMessage {
     pub recipient: SocketAddr,
-    pub agent_states: Vec<AgentState>,
+
+    #[serde(flatten)]
+    pub payload: Payload,
+}
+
+/// A message that is serialized once and shared by every client that it is
+/// sent to. Unlike an `OutgoingMessage` it has no recipient field.
+#[derive(Clone, Debug)]
+pub struct SharedMessage {
+    buffer: Arc<SharedBuffer>,
+}
+
+#[derive(Debug)]
+struct SharedBuffer {
+    json: String,
+
+    /// The compressed message, created when it is first sent to a client
+    /// that asked for compression.
+    compressed: OnceLock<Vec<u8>>,
+}
+
+/// A shared message and the clients to send it to.
+#[derive(Debug)]
+pub struct Broadcast {
+    pub recipients: Vec<SocketAddr>,
+    pub message: SharedMessage,
+}
+
+/// Contents of a message sent by the server. Messages are JSON objects with a
+/// `type` field naming the kind of message, such as
+/// `{"type": "world_state", "agent_states": [...]}`.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Payload {
+    /// Sent to a client as soon as it connects, before any other message.
+    Welcome(Welcome),
+
+    /// The state of every agent.
+    WorldState { agent_states: Vec<AgentState> },
+
+    /// Time taken by the systems of the simulation, sent in reply to a
+    /// `get_profile` command.
+    Profile(ProfileReport),
+
+    /// Statistics of the flock, sent in reply to a `get_analytics` command.
+    Analytics(FlockStats),
+
+    /// Explains why a request from the client was rejected.
+    Error { message: String },
+}
+
+/// Describes the server and the world to a newly connected client.
+#[derive(Serialize, Debug)]
+pub struct Welcome {
+    pub protocol_version: u32,
+
+    /// Identifier assigned to the client for the lifetime of its connection.
+    pub client_id: u64,
+
+    /// Name of the room that the client is in.
+    pub room: String,
+
+    pub role: Role,
+    pub scenario: String,
+
+    /// Area in which agents may move.
+    pub bounds: BoundingBox,
+
+    /// Width and height of a square grid cell in meters.
+    pub cell_size: f32,
+
+    /// Number of grid columns and rows.
+    pub grid_size: (usize, usize),
+
+    /// Duration of a frame in seconds.
+    pub frame_duration: f64,
+
+    /// Number of the current frame.
+    pub frame: u64,
+}
+
+/// Collective behavior of the flock during a frame.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FlockStats {
+    pub frame: u64,
+    pub agents: usize,
+
+    /// Length of the mean heading. It is 1 when every sheep heads the same
+    /// way and near 0 when headings are disordered.
+    pub polarization: f32,
+
+    /// Mean angular momentum of the headings around the flock's center of
+    /// mass. It is 1 when the flock circles around its center.
+    pub milling: f32,
+
+    /// Mean distance in meters from each sheep to its nearest neighbor, or
+    /// `None` if there are fewer than two sheep.
+    pub mean_nearest_neighbor_distance: Option<f32>,
+
+    /// Number of clusters, and their sizes from largest to smallest.
+    pub clusters: usize,
+    pub cluster_sizes: Vec<usize>,
+
+    /// Fraction of the sheep with each behavior.
+    pub behavior_fractions: BTreeMap<String, f32>,
+}
+
+/// Time taken by each system of the simulation. Times are in seconds.
+#[derive(Serialize, Debug)]
+pub struct ProfileReport {
+    /// Time that a frame may take without delaying the next one.
+    pub budget: f64,
+
+    /// Number of frames profiled, and the number of them that took longer
+    /// than the budget.
+    pub frames: u64,
+    pub overruns: u64,
+
+    pub last_frame: Option<FrameProfile>,
+
+    /// Systems ordered from the one that has taken the most time in total.
+    pub systems: Vec<SystemProfile>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FrameProfile {
+    pub number: u64,
+    pub duration: f64,
+
+    /// Time taken by each system that ran during the frame.
+    pub systems: Vec<(String, f64)>,
 }
 
 #[derive(Serialize, Debug)]
+pub struct SystemProfile {
+    pub name: String,
+    pub mean: f64,
+    pub max: f64,
+
+    /// Fraction of the time taken by all systems that was taken by this one.
+    pub share: f64,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct AgentState {
     pub position: Option<(f32, f32)>,
     pub heading: Option<f32>,
 }
 
+impl AgentState {
+    pub fn new(x: f32, y: f32, heading: f32) -> AgentState {
+        AgentState {
+            position: Some((x, y)),
+            heading: Some(heading),
+        }
+    }
+}
+
 impl OutgoingMessage {
-    pub fn new(recipient: SocketAddr) -> OutgoingMessage {
+    pub fn welcome(recipient: SocketAddr, welcome: Welcome) -> OutgoingMessage {
         OutgoingMessage {
             recipient,
-            agent_states: vec![],
+            payload: Payload::Welcome(welcome),
         }
     }
 
-    pub fn with_agent_state(&mut self, x: f32, y: f32, heading: f32) -> &mut OutgoingMessage {
-        self.agent_states.push(AgentState {
-            position: Some((x, y)),
-            heading: Some(heading),
-        });
-        self
+    pub fn profile(recipient: SocketAddr, report: ProfileReport) -> OutgoingMessage {
+        OutgoingMessage {
+            recipient,
+            payload: Payload::Profile(report),
+        }
+    }
+
+    pub fn analytics(recipient: SocketAddr, stats: FlockStats) -> OutgoingMessage {
+        OutgoingMessage {
+            recipient,
+            payload: Payload::Analytics(stats),
+        }
+    }
+
+    pub fn error(recipient: SocketAddr, message: String) -> OutgoingMessage {
+        OutgoingMessage {
+            recipient,
+            payload: Payload::Error { message },
+        }
+    }
+}
+
+impl SharedMessage {
+    /// Serializes a message with the state of every agent.
+    pub fn world_state(agent_states: Vec<AgentState>) -> SharedMessage {
+        let json = serde_json::to_string(&Payload::WorldState { agent_states })
+            .expect("World states are always serializable.");
+        SharedMessage {
+            buffer: Arc::new(SharedBuffer {
+                json,
+                compressed: OnceLock::new(),
+            }),
+        }
+    }
+
+    pub fn json(&self) -> &str {
+        &self.buffer.json
+    }
+
+    /// Gets the message as a compressed binary message. The message is only
+    /// compressed once no matter how many clients it is sent to.
+    pub fn compressed(&self) -> NetworkResult<Message> {
+        let data = match self.buffer.compressed.get() {
+            Some(data) => data,
+            None => {
+                let data = compression::compress_text(&self.buffer.json)?;
+                self.buffer.compressed.get_or_init(|| data)
+            }
+        };
+        Ok(Message::Binary(data.clone()))
     }
 }
 
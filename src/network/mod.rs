@@ -1,76 +1,298 @@
+mod auth;
 pub mod channel;
+mod compression;
 mod error;
 mod message;
+mod options;
+mod queue;
+mod role;
+mod tls;
 
-pub use message::{IncomingMessage, OutgoingMessage};
+pub use auth::Auth;
+pub use message::{
+    AgentState, Broadcast, ClientCommand, FlockStats, FrameProfile, IncomingMessage,
+    OutgoingMessage, ProfileReport, Query, SharedMessage, SystemProfile, Welcome, PROTOCOL_VERSION,
+};
+pub use options::Options;
+pub use role::Role;
+pub use tls::TlsFiles;
 
+use crate::metrics::Metrics;
+use crate::shutdown::Shutdown;
+use channel::ClientMessage;
 use error::NetworkResult;
 use futures::sink::SinkExt;
-use futures_channel::mpsc::unbounded;
-use futures_util::{future, pin_mut, stream::TryStreamExt, StreamExt};
+use futures_util::{
+    future, pin_mut,
+    stream::{self, TryStreamExt},
+    StreamExt,
+};
+use percent_encoding::percent_decode_str;
+use queue::ClientQueue;
 use std::{
+    borrow::Cow,
     convert::TryFrom,
     net::SocketAddr,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    time::{delay_for, interval},
+};
+use tokio_tls::TlsAcceptor;
+use tracing::{debug, info, info_span, trace, Instrument};
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request},
+    http::{header::WWW_AUTHENTICATE, HeaderValue, StatusCode},
+    protocol::Message,
 };
-use tokio::net::{TcpListener, TcpStream};
-use tungstenite::protocol::Message;
 
-/// Handles a TCP connection by attempting to establish a WebSocket connection.
-async fn handle_connection(
+/// Gets the name of the room requested by the path of a WebSocket URL. The root
+/// path requests the default room.
+fn room_from_path(path: &str) -> Option<String> {
+    let name = path.trim_matches('/');
+    if name.is_empty() {
+        Some(DEFAULT_ROOM.to_string())
+    } else if is_valid_room_name(name) {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+/// Gets the percent-decoded value of a parameter in the query string of a
+/// URL.
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<Cow<'a, str>> {
+    let value = query?
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)?;
+    percent_decode_str(value).decode_utf8().ok()
+}
+
+/// Returns true if the name can be used for a room. Names are made of ASCII
+/// letters, digits, '-' and '_'.
+pub fn is_valid_room_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Creates a response that rejects a WebSocket handshake.
+fn error_response(status: StatusCode, reason: &str) -> ErrorResponse {
+    let mut res = ErrorResponse::new(Some(reason.to_string()));
+    *res.status_mut() = status;
+    res
+}
+
+/// Maximum time to wait for connections to close after close messages are
+/// sent to clients.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Name of the room that clients join when the WebSocket URL doesn't name one.
+pub const DEFAULT_ROOM: &str = "default";
+
+/// Handles a TCP connection, first performing a TLS handshake if the acceptor
+/// is given.
+async fn accept_connection(
     channels: Arc<Mutex<channel::SenderManager>>,
-    raw_stream: TcpStream,
+    options: Options,
+    tls: Option<TlsAcceptor>,
+    metrics: Arc<Metrics>,
+    stream: TcpStream,
     addr: SocketAddr,
 ) -> NetworkResult<()> {
-    println!("Incoming TCP connection from: {}", addr);
-    // TODO: Include error message: "Error during the websocket handshake
-    // occurred."
-    let ws_stream = tokio_tungstenite::accept_async(raw_stream).await?;
-    println!("WebSocket connection established: {}", addr);
+    debug!("Incoming TCP connection");
+    match tls {
+        Some(acceptor) => {
+            let stream = acceptor.accept(stream).await?;
+            handle_connection(channels, options, metrics, stream, addr).await
+        }
+        None => handle_connection(channels, options, metrics, stream, addr).await,
+    }
+}
 
-    // Insert the sender part of this channel into the channel manager.
-    let (sender, receiver) = unbounded();
-    channels.lock().unwrap().insert_client_sender(addr, sender);
+/// Reason given to clients whose connections are closed for being idle.
+const IDLE_REASON: &str = "Connection timed out";
+
+/// Handles a TCP connection by attempting to establish a WebSocket connection.
+/// The client is pinged periodically and disconnected if nothing is received
+/// from it within the idle timeout.
+async fn handle_connection<S>(
+    channels: Arc<Mutex<channel::SenderManager>>,
+    options: Options,
+    metrics: Arc<Metrics>,
+    raw_stream: S,
+    addr: SocketAddr,
+) -> NetworkResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // The client chooses a room with the path of the WebSocket URL and
+    // negotiates its role and compression with the query string.
+    let mut room = None;
+    let mut role = Role::Observer;
+    let mut compressed = false;
+    #[allow(clippy::result_large_err)] // The error response type is tungstenite's.
+    let ws_stream = tokio_tungstenite::accept_hdr_async(raw_stream, |req: &Request, res| {
+        if !options.auth.authenticate(req) {
+            let mut res = error_response(StatusCode::UNAUTHORIZED, "Missing or invalid token");
+            res.headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return Err(res);
+        }
+        room = room_from_path(req.uri().path());
+        role = Role::from_query(req.uri().query(), &options);
+        compressed = options.compression
+            && query_param(req.uri().query(), "compression").as_deref() == Some("deflate");
+        match room {
+            Some(_) => Ok(res),
+            None => Err(error_response(StatusCode::NOT_FOUND, "Invalid room name")),
+        }
+    })
+    .await?;
+    let room = room.expect("Room was validated during the handshake.");
+    info!(%room, ?role, "WebSocket connection established");
+
+    // Insert this client's queue into the channel manager and let the
+    // simulation greet the client.
+    let queue = Arc::new(ClientQueue::new());
+    {
+        let mut channels = channels.lock().unwrap();
+        let client_id = channels.insert_client_queue(addr, queue.clone());
+        channels.send_to_sim(IncomingMessage::Connected {
+            sender: addr,
+            client_id,
+            room,
+            role,
+        });
+    }
 
     let (ws_out, ws_in) = ws_stream.split();
 
     // Handle each incoming WS message by sending a message on the sim channel.
+    // Commands that the client's role doesn't permit are rejected instead. Any
+    // message, including a pong, shows that the client is still alive.
+    let last_received = Mutex::new(Instant::now());
     let handle_incoming_messages = ws_in.try_for_each(|ws_msg| {
-        println!(
-            "Received a message from {}: {}",
-            addr,
-            ws_msg.to_text().unwrap()
+        *last_received.lock().unwrap() = Instant::now();
+        if let Message::Ping(_) | Message::Pong(_) = ws_msg {
+            return future::ok(());
+        }
+        trace!(
+            message = ws_msg.to_text().unwrap_or("<binary>"),
+            "Received a message"
         );
         if let Ok(incoming_msg) = message::IncomingMessage::try_new(addr, ws_msg) {
-            channels.lock().unwrap().send_to_sim(incoming_msg);
+            match incoming_msg.command() {
+                Some(cmd) if !role.permits(cmd) => {
+                    let error = "Only controllers may send this command".to_string();
+                    queue.push(ClientMessage::Outgoing(OutgoingMessage::error(addr, error)));
+                }
+                _ => channels.lock().unwrap().send_to_sim(incoming_msg),
+            }
         }
         future::ok(())
     });
 
-    // Forward messages recieved on this handler's channel to the outgoing WS
-    // stream.
-    let handle_outgoing_messages = receiver
-        .map(Message::try_from)
-        .forward(ws_out.sink_err_into());
+    // Forward messages pushed onto this client's queue to the outgoing WS
+    // stream, compressing them if the client asked for it.
+    let handle_outgoing_messages = stream::unfold(queue.clone(), |queue| async move {
+        queue.pop().await.map(|msg| (msg, queue))
+    })
+    .map(|msg| match msg {
+        ClientMessage::WorldState(msg) if compressed => msg.compressed(),
+        msg if compressed => Message::try_from(msg).and_then(compression::compress),
+        msg => Message::try_from(msg),
+    })
+    .inspect_ok(|msg| metrics.add_sent_bytes(msg.len()))
+    .forward(ws_out.sink_err_into());
+
+    // Ping the client until it has been idle for too long. The connection is
+    // dropped if the close message can't be sent in time.
+    let heartbeat = async {
+        let mut pings = interval(options.ping_interval());
+        loop {
+            pings.tick().await;
+            if last_received.lock().unwrap().elapsed() > options.idle_timeout {
+                break;
+            }
+            queue.push(ClientMessage::Ping);
+        }
+        info!("Timed out");
+        queue.close(IDLE_REASON);
+        delay_for(CLOSE_TIMEOUT).await;
+    };
 
-    pin_mut!(handle_incoming_messages, handle_outgoing_messages);
-    future::select(handle_incoming_messages, handle_outgoing_messages).await;
+    pin_mut!(
+        handle_incoming_messages,
+        handle_outgoing_messages,
+        heartbeat
+    );
+    future::select(
+        future::select(handle_incoming_messages, handle_outgoing_messages),
+        heartbeat,
+    )
+    .await;
 
-    // Client is disconnected so remove it from the clients.
-    println!("{} disconnected", &addr);
-    channels.lock().unwrap().remove_client_sender(&addr);
+    // Client is disconnected so remove it from the clients and let the
+    // simulation clean up after it.
+    info!("Disconnected");
+    let mut channels = channels.lock().unwrap();
+    channels.remove_client_queue(&addr);
+    channels.send_to_sim(IncomingMessage::Disconnected(addr));
 
     Ok(())
 }
 
 /// Returns a future thant accepts each new connection from the TCP listener in
-/// a separate task.
+/// a separate task. New connections are no longer accepted once the server
+/// starts shutting down.
 pub async fn accept_connections(
     listener: &mut TcpListener,
     channels: Arc<Mutex<channel::SenderManager>>,
+    options: Options,
+    tls: Option<TlsAcceptor>,
+    metrics: Arc<Metrics>,
+    mut shutdown: Shutdown,
 ) {
-    while let Ok((stream, addr)) = listener.accept().await {
-        // Spawn separate task for handing each connection.
-        tokio::spawn(handle_connection(channels.clone(), stream, addr));
+    let accept_loop = async {
+        while let Ok((stream, addr)) = listener.accept().await {
+            // Spawn separate task for handing each connection. Everything
+            // logged while handling it is tagged with the client's address.
+            let connection = accept_connection(
+                channels.clone(),
+                options.clone(),
+                tls.clone(),
+                metrics.clone(),
+                stream,
+                addr,
+            );
+            let task = async {
+                if let Err(err) = connection.await {
+                    debug!("Connection failed: {}", err);
+                }
+            };
+            tokio::spawn(task.instrument(info_span!("client", %addr)));
+        }
+    };
+    let shutdown = shutdown.wait();
+    pin_mut!(accept_loop, shutdown);
+    future::select(accept_loop, shutdown).await;
+}
+
+/// Sends a close frame with the reason to every connected client and waits
+/// for the connections to close.
+pub async fn close_connections(channels: Arc<Mutex<channel::SenderManager>>, reason: &str) {
+    channels.lock().unwrap().close_clients(reason);
+
+    let deadline = Instant::now() + CLOSE_TIMEOUT;
+    while channels.lock().unwrap().client_addrs().next().is_some() && Instant::now() < deadline {
+        delay_for(Duration::from_millis(10)).await;
     }
 }
@@ -0,0 +1,46 @@
+use super::auth::Auth;
+use super::tls::TlsFiles;
+use std::time::Duration;
+
+/// Options that control how client connections are handled.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Time after which a connection is closed if nothing has been received
+    /// from the client. Clients are pinged often enough that a live client
+    /// answers well within the timeout.
+    pub idle_timeout: Duration,
+
+    /// Tokens that grant clients the controller role. If there are no tokens
+    /// then every client is a controller.
+    pub controller_tokens: Vec<String>,
+
+    /// Credentials that clients must present when they connect.
+    pub auth: Auth,
+
+    /// Files from which the TLS identity is read. Connections are not
+    /// encrypted if no files are given.
+    pub tls: Option<TlsFiles>,
+
+    /// If true then clients that connect with `?compression=deflate` are sent
+    /// compressed binary frames instead of text frames.
+    pub compression: bool,
+}
+
+impl Options {
+    /// Time between pings sent to each client.
+    pub fn ping_interval(&self) -> Duration {
+        self.idle_timeout / 3
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            idle_timeout: Duration::from_secs(30),
+            controller_tokens: vec![],
+            auth: Auth::default(),
+            tls: None,
+            compression: true,
+        }
+    }
+}
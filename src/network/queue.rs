@@ -0,0 +1,191 @@
+use super::channel::ClientMessage;
+use super::message::SharedMessage;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+/// Maximum number of control messages that may wait to be sent to a client.
+const CONTROL_CAPACITY: usize = 64;
+
+/// Maximum time that a client may keep missing world states before it is
+/// disconnected.
+const MAX_LAG: Duration = Duration::from_secs(5);
+
+/// Reason given to clients that are disconnected for falling behind.
+const LAG_REASON: &str = "Client fell too far behind";
+
+/// A bounded queue of messages waiting to be sent to a client.
+///
+/// Only the most recent world state is kept, since a client that has not
+/// received a world state yet has no use for an older one. Control messages,
+/// such as welcome messages, pings and close messages, are kept in the order
+/// they were pushed. A client that can't keep up with either is disconnected.
+pub struct ClientQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+struct QueueState {
+    control: VecDeque<ClientMessage>,
+
+    /// The most recent world state that has not been sent.
+    world_state: Option<SharedMessage>,
+
+    /// The time at which an unsent world state was first replaced since the
+    /// client last received one.
+    behind_since: Option<Instant>,
+
+    /// If true then no more messages are accepted.
+    closed: bool,
+}
+
+impl ClientQueue {
+    pub fn new() -> ClientQueue {
+        ClientQueue {
+            state: Mutex::new(QueueState {
+                control: VecDeque::new(),
+                world_state: None,
+                behind_since: None,
+                closed: false,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Pushes a message onto the queue. If the client has fallen too far
+    /// behind then the pending messages are discarded and the queue is
+    /// closed.
+    pub fn push(&self, msg: ClientMessage) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+
+        match msg {
+            ClientMessage::WorldState(world_state) => {
+                if state.world_state.replace(world_state).is_some() {
+                    let now = Instant::now();
+                    let behind_since = *state.behind_since.get_or_insert(now);
+                    if now - behind_since > MAX_LAG {
+                        state.close_lagging();
+                    }
+                }
+            }
+            ClientMessage::Outgoing(_) | ClientMessage::Ping => state.control.push_back(msg),
+            ClientMessage::Close(_) => {
+                state.control.push_back(msg);
+                state.closed = true;
+            }
+        }
+        if state.control.len() > CONTROL_CAPACITY {
+            state.close_lagging();
+        }
+        self.notify.notify();
+    }
+
+    /// Pushes a close message with the reason onto the queue. No more
+    /// messages are accepted afterwards.
+    pub fn close(&self, reason: &str) {
+        self.push(ClientMessage::Close(reason.to_string()));
+    }
+
+    /// Gets the number of messages waiting to be sent to the client.
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.control.len() + usize::from(state.world_state.is_some())
+    }
+
+    /// Waits for the next message to send to the client. Control messages are
+    /// sent before the world state. Returns `None` once the queue is closed
+    /// and empty.
+    pub async fn pop(&self) -> Option<ClientMessage> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(msg) = state.control.pop_front() {
+                    return Some(msg);
+                }
+                if state.closed {
+                    return None;
+                }
+                if let Some(world_state) = state.world_state.take() {
+                    state.behind_since = None;
+                    return Some(ClientMessage::WorldState(world_state));
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+impl QueueState {
+    /// Discards the pending messages and closes the queue with a close
+    /// message.
+    fn close_lagging(&mut self) {
+        self.control.clear();
+        self.world_state = None;
+        self.control
+            .push_back(ClientMessage::Close(LAG_REASON.to_string()));
+        self.closed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientQueue, CONTROL_CAPACITY, LAG_REASON};
+    use crate::network::channel::ClientMessage;
+    use crate::network::{AgentState, SharedMessage};
+    use futures::executor::block_on;
+
+    fn world_state(x: f32) -> ClientMessage {
+        ClientMessage::WorldState(SharedMessage::world_state(vec![AgentState::new(
+            x, 0.0, 0.0,
+        )]))
+    }
+
+    #[test]
+    fn world_states_are_coalesced() {
+        let queue = ClientQueue::new();
+        queue.push(world_state(1.0));
+        queue.push(world_state(2.0));
+        queue.close("done");
+
+        match block_on(queue.pop()) {
+            Some(ClientMessage::Close(reason)) => assert_eq!(reason, "done"),
+            _ => panic!("Expected the close message first."),
+        }
+        assert!(block_on(queue.pop()).is_none());
+
+        let queue = ClientQueue::new();
+        queue.push(world_state(1.0));
+        queue.push(world_state(2.0));
+        match block_on(queue.pop()) {
+            Some(ClientMessage::WorldState(msg)) => {
+                let json: serde_json::Value = serde_json::from_str(msg.json()).unwrap();
+                assert_eq!(json["agent_states"][0]["position"][0], 2.0);
+            }
+            _ => panic!("Expected the latest world state."),
+        }
+    }
+
+    #[test]
+    fn overflowing_control_messages_close_the_queue() {
+        let queue = ClientQueue::new();
+        {
+            let mut state = queue.state.lock().unwrap();
+            for _ in 0..CONTROL_CAPACITY {
+                state.control.push_back(ClientMessage::Ping);
+            }
+        }
+        queue.close("done");
+
+        match block_on(queue.pop()) {
+            Some(ClientMessage::Close(reason)) => assert_eq!(reason, LAG_REASON),
+            _ => panic!("Expected the lag close message."),
+        }
+        assert!(block_on(queue.pop()).is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use super::message::ClientCommand;
+use super::options::Options;
+use super::{auth, query_param};
+use serde::{Deserialize, Serialize};
+
+/// What a client is allowed to do.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Only receives the world state.
+    Observer,
+
+    /// May also send commands that change the simulation.
+    Controller,
+}
+
+impl Role {
+    /// Gets the role negotiated by the query string of a WebSocket URL. A
+    /// client is a controller if it presents one of the controller tokens, as
+    /// in `?token=secret`, or if no tokens are configured. A client can ask to
+    /// be an observer with `?role=observer`.
+    pub fn from_query(query: Option<&str>, options: &Options) -> Role {
+        let is_authorized = options.controller_tokens.is_empty()
+            || query_param(query, "token")
+                .is_some_and(|token| auth::is_listed(&options.controller_tokens, &token));
+        if is_authorized && query_param(query, "role").as_deref() != Some("observer") {
+            Role::Controller
+        } else {
+            Role::Observer
+        }
+    }
+
+    /// Returns true if a client with the role may send the command.
+    pub fn permits(self, command: &ClientCommand) -> bool {
+        match command {
+            ClientCommand::JoinRoom { .. }
+            | ClientCommand::GetProfile
+            | ClientCommand::GetAnalytics => true,
+            _ => self == Role::Controller,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Role;
+    use crate::network::Options;
+
+    #[test]
+    fn role_from_query() {
+        let mut options = Options::default();
+        assert_eq!(Role::from_query(None, &options), Role::Controller);
+        assert_eq!(
+            Role::from_query(Some("role=observer"), &options),
+            Role::Observer
+        );
+
+        options.controller_tokens = vec!["secret".to_string()];
+        assert_eq!(Role::from_query(None, &options), Role::Observer);
+        assert_eq!(
+            Role::from_query(Some("token=wrong"), &options),
+            Role::Observer
+        );
+        assert_eq!(
+            Role::from_query(Some("room=a&token=secret"), &options),
+            Role::Controller
+        );
+    }
+}
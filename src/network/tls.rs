@@ -0,0 +1,26 @@
+use super::error::NetworkResult;
+use native_tls::Identity;
+use std::{fs, path::PathBuf};
+use tokio_tls::TlsAcceptor;
+
+/// Files from which the server's TLS identity is read.
+#[derive(Clone, Debug)]
+pub struct TlsFiles {
+    /// PEM encoded certificate chain.
+    pub cert: PathBuf,
+
+    /// PEM encoded PKCS #8 private key.
+    pub key: PathBuf,
+}
+
+impl TlsFiles {
+    /// Creates an acceptor that performs the server side of TLS handshakes
+    /// with the identity in the files.
+    pub fn acceptor(&self) -> NetworkResult<TlsAcceptor> {
+        let cert = fs::read(&self.cert)?;
+        let key = fs::read(&self.key)?;
+        let identity = Identity::from_pkcs8(&cert, &key)?;
+        let acceptor = native_tls::TlsAcceptor::new(identity)?;
+        Ok(TlsAcceptor::from(acceptor))
+    }
+}
@@ -0,0 +1,54 @@
+use futures_util::{future, pin_mut};
+use std::io;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+
+/// Reason given to clients when their connections are closed because the
+/// server is shutting down.
+pub const REASON: &str = "Server is shutting down";
+
+/// Notifies the tasks holding a `Shutdown` that the server is shutting down.
+pub struct Trigger {
+    sender: watch::Sender<bool>,
+}
+
+/// Waits for the server to shut down. Each task that needs to stop cleanly
+/// holds its own clone.
+#[derive(Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+}
+
+/// Creates a shutdown trigger and the receiving end that waits for it.
+pub fn channel() -> (Trigger, Shutdown) {
+    let (sender, receiver) = watch::channel(false);
+    (Trigger { sender }, Shutdown { receiver })
+}
+
+impl Trigger {
+    pub fn fire(&self) {
+        let _ = self.sender.broadcast(true);
+    }
+}
+
+impl Shutdown {
+    /// Waits until shutdown is triggered. Returns immediately if the trigger
+    /// has already been fired or dropped.
+    pub async fn wait(&mut self) {
+        while let Some(false) = self.receiver.recv().await {}
+    }
+}
+
+/// Waits until the process receives SIGINT or SIGTERM.
+pub async fn signal_received() -> io::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let interrupt = tokio::signal::ctrl_c();
+    let terminate = terminate.recv();
+    pin_mut!(interrupt, terminate);
+    if let future::Either::Left((Err(err), _)) = future::select(interrupt, terminate).await {
+        return Err(err);
+    }
+    Ok(())
+}
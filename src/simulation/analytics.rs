@@ -0,0 +1,304 @@
+use super::component::SheepBehavior;
+use crate::network::FlockStats;
+use nalgebra::Vector2;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Position, heading and behavior of a sheep from which the flock statistics
+/// are computed.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub position: Vector2<f32>,
+
+    /// Unit vector in the direction of the sheep's heading.
+    pub heading: Vector2<f32>,
+
+    pub behavior: SheepBehavior,
+}
+
+/// The statistics of the flock during the most recent frame, and the file to
+/// which the statistics of each frame are written.
+#[derive(Default)]
+pub struct Analytics {
+    pub latest: FlockStats,
+    pub writer: Option<AnalyticsWriter>,
+
+    /// Sheep that are at most this far apart are in the same cluster. The
+    /// cell size of the world map is used if no distance is given.
+    pub cluster_distance: Option<f32>,
+}
+
+/// Computes the statistics of the flock. Sheep belong to the same cluster if
+/// they are connected by a chain of sheep that are at most `cluster_distance`
+/// apart.
+pub fn flock_stats(frame: u64, samples: &[Sample], cluster_distance: f32) -> FlockStats {
+    let count = samples.len();
+    let mut stats = FlockStats {
+        frame,
+        agents: count,
+        ..FlockStats::default()
+    };
+    if count == 0 {
+        return stats;
+    }
+    let n = count as f32;
+
+    let heading_sum: Vector2<f32> = samples.iter().map(|s| s.heading).sum();
+    stats.polarization = heading_sum.norm() / n;
+
+    // Angular momentum of unit headings around the center of mass. It is 1
+    // when the flock mills in a circle and 0 when it moves in a line.
+    let center = samples.iter().map(|s| s.position).sum::<Vector2<f32>>() / n;
+    let momentum: f32 = samples
+        .iter()
+        .filter_map(|s| {
+            let r = s.position - center;
+            r.try_normalize(f32::EPSILON)
+                .map(|r| r.x * s.heading.y - r.y * s.heading.x)
+        })
+        .sum();
+    stats.milling = momentum.abs() / n;
+
+    let grid = SpatialHash::new(samples, cluster_distance);
+    if count > 1 {
+        let total: f32 = (0..count).map(|i| grid.nearest_distance(samples, i)).sum();
+        stats.mean_nearest_neighbor_distance = Some(total / n);
+    }
+    stats.cluster_sizes = grid.cluster_sizes(samples);
+    stats.clusters = stats.cluster_sizes.len();
+
+    let mut behavior_counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    for s in samples {
+        *behavior_counts.entry(s.behavior.name()).or_insert(0) += 1;
+    }
+    stats.behavior_fractions = [
+        SheepBehavior::Stationary,
+        SheepBehavior::Walking,
+        SheepBehavior::Running,
+    ]
+    .iter()
+    .map(|behavior| {
+        let count = behavior_counts.get(behavior.name()).copied().unwrap_or(0);
+        (behavior.name().to_string(), count as f32 / n)
+    })
+    .collect();
+
+    stats
+}
+
+/// Indices of the samples in each square cell of an unbounded grid.
+struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+
+    /// Smallest and largest cell coordinates that contain samples.
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl SpatialHash {
+    fn new(samples: &[Sample], cell_size: f32) -> SpatialHash {
+        let mut grid = SpatialHash {
+            cell_size,
+            cells: HashMap::new(),
+            min: (i32::MAX, i32::MAX),
+            max: (i32::MIN, i32::MIN),
+        };
+        for (i, s) in samples.iter().enumerate() {
+            let cell = grid.cell_of(s.position);
+            grid.min = (grid.min.0.min(cell.0), grid.min.1.min(cell.1));
+            grid.max = (grid.max.0.max(cell.0), grid.max.1.max(cell.1));
+            grid.cells.entry(cell).or_default().push(i);
+        }
+        grid
+    }
+
+    fn cell_of(&self, pos: Vector2<f32>) -> (i32, i32) {
+        (
+            (pos.x / self.cell_size).floor() as i32,
+            (pos.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Gets the indices of the samples in the cells whose coordinates differ
+    /// from the given cell's by exactly `ring` in at least one axis.
+    fn ring(&self, (x, y): (i32, i32), ring: i32) -> impl Iterator<Item = usize> + '_ {
+        (y - ring..=y + ring)
+            .flat_map(move |cy| (x - ring..=x + ring).map(move |cx| (cx, cy)))
+            .filter(move |(cx, cy)| (cx - x).abs() == ring || (cy - y).abs() == ring)
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+
+    /// Gets the distance from a sample to the nearest other sample, searching
+    /// outward one ring of cells at a time.
+    fn nearest_distance(&self, samples: &[Sample], i: usize) -> f32 {
+        let pos = samples[i].position;
+        let center = self.cell_of(pos);
+        let max_ring = (self.max.0 - self.min.0).max(self.max.1 - self.min.1);
+        let mut nearest = f32::INFINITY;
+        for ring in 0..=max_ring + 1 {
+            for j in self.ring(center, ring).filter(|&j| j != i) {
+                nearest = nearest.min((samples[j].position - pos).norm());
+            }
+            // Samples in the next ring are at least this far away.
+            if nearest <= ring as f32 * self.cell_size {
+                break;
+            }
+        }
+        nearest
+    }
+
+    /// Gets the size of each cluster from largest to smallest.
+    fn cluster_sizes(&self, samples: &[Sample]) -> Vec<usize> {
+        let mut parents: Vec<usize> = (0..samples.len()).collect();
+        for (i, s) in samples.iter().enumerate() {
+            let cell = self.cell_of(s.position);
+            let neighbors = self.ring(cell, 0).chain(self.ring(cell, 1));
+            for j in neighbors.filter(|&j| j > i) {
+                if (samples[j].position - s.position).norm() <= self.cell_size {
+                    let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for i in 0..samples.len() {
+            *sizes.entry(root(&mut parents, i)).or_insert(0) += 1;
+        }
+        let mut sizes: Vec<usize> = sizes.into_values().collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes
+    }
+}
+
+/// Finds the representative of a sample's cluster, flattening the path to it.
+fn root(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut i = i;
+    while parents[i] != root {
+        let next = parents[i];
+        parents[i] = root;
+        i = next;
+    }
+    root
+}
+
+/// Writes the flock statistics of each frame to a CSV file.
+pub struct AnalyticsWriter {
+    writer: BufWriter<File>,
+}
+
+impl AnalyticsWriter {
+    /// Creates a CSV file at the given path and writes its header.
+    pub fn create(path: &Path) -> io::Result<AnalyticsWriter> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(
+            writer,
+            "frame,agents,polarization,milling,mean_nearest_neighbor_distance,clusters,\
+             cluster_sizes,stationary,walking,running"
+        )?;
+        Ok(AnalyticsWriter { writer })
+    }
+
+    /// Writes a row with the statistics. Cluster sizes are separated by
+    /// spaces.
+    pub fn write(&mut self, stats: &FlockStats) -> io::Result<()> {
+        let fraction = |name: &str| stats.behavior_fractions.get(name).copied().unwrap_or(0.0);
+        let cluster_sizes: Vec<String> = stats.cluster_sizes.iter().map(usize::to_string).collect();
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{}",
+            stats.frame,
+            stats.agents,
+            stats.polarization,
+            stats.milling,
+            stats
+                .mean_nearest_neighbor_distance
+                .map_or_else(String::new, |d| d.to_string()),
+            stats.clusters,
+            cluster_sizes.join(" "),
+            fraction("stationary"),
+            fraction("walking"),
+            fraction("running"),
+        )
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flock_stats, Sample};
+    use crate::simulation::component::SheepBehavior;
+    use nalgebra::Vector2;
+    use std::f32::consts::PI;
+
+    fn sample(x: f32, y: f32, angle: f32) -> Sample {
+        Sample {
+            position: Vector2::new(x, y),
+            heading: Vector2::new(angle.cos(), angle.sin()),
+            behavior: SheepBehavior::Walking,
+        }
+    }
+
+    #[test]
+    fn aligned_flocks_are_polarized() {
+        let aligned: Vec<Sample> = (0..4).map(|i| sample(i as f32, 0.0, 0.5)).collect();
+        let stats = flock_stats(0, &aligned, 1.0);
+        assert!((stats.polarization - 1.0).abs() < 1e-5);
+        assert!(stats.milling < 1e-5);
+
+        let opposed = [sample(0.0, 0.0, 0.0), sample(1.0, 0.0, PI)];
+        assert!(flock_stats(0, &opposed, 1.0).polarization < 1e-5);
+    }
+
+    #[test]
+    fn circling_flocks_mill() {
+        let circle: Vec<Sample> = (0..8)
+            .map(|i| {
+                let angle = i as f32 * PI / 4.0;
+                sample(10.0 * angle.cos(), 10.0 * angle.sin(), angle + PI / 2.0)
+            })
+            .collect();
+        let stats = flock_stats(0, &circle, 1.0);
+        assert!((stats.milling - 1.0).abs() < 1e-5);
+        assert!(stats.polarization < 1e-5);
+    }
+
+    #[test]
+    fn nearby_sheep_form_clusters() {
+        let samples = [
+            sample(0.0, 0.0, 0.0),
+            sample(1.5, 0.0, 0.0),
+            sample(3.0, 0.5, 0.0),
+            sample(20.0, 20.0, 0.0),
+            sample(21.0, 20.0, 0.0),
+            sample(-40.0, 5.0, 0.0),
+        ];
+        let stats = flock_stats(3, &samples, 2.0);
+        assert_eq!(stats.clusters, 3);
+        assert_eq!(stats.cluster_sizes, [3, 2, 1]);
+
+        // The lone sheep's nearest neighbor is the one at the origin.
+        let expected = (1.5 + 1.5 + 2.5f32.sqrt() + 1.0 + 1.0 + 1625f32.sqrt()) / 6.0;
+        let mean = stats.mean_nearest_neighbor_distance.unwrap();
+        assert!((mean - expected).abs() < 1e-4, "{} != {}", mean, expected);
+        assert_eq!(stats.behavior_fractions["walking"], 1.0);
+        assert_eq!(stats.behavior_fractions["running"], 0.0);
+    }
+}
@@ -0,0 +1,30 @@
+/// Tracks when the world state was last broadcast to clients.
+#[derive(Clone, Copy, Debug)]
+pub struct BroadcastSchedule {
+    /// Number of frames between broadcasts.
+    pub interval: u64,
+
+    /// Number of the frame during which the world state was last broadcast.
+    pub last_frame: Option<u64>,
+}
+
+impl BroadcastSchedule {
+    pub fn new(interval: u64) -> BroadcastSchedule {
+        BroadcastSchedule {
+            interval: interval.max(1),
+            last_frame: None,
+        }
+    }
+
+    /// Returns true if the world state should be broadcast during the frame
+    /// and records the broadcast.
+    pub fn try_broadcast(&mut self, frame: u64) -> bool {
+        let is_due = self
+            .last_frame
+            .is_none_or(|last| frame < last || frame >= last + self.interval);
+        if is_due {
+            self.last_frame = Some(frame);
+        }
+        is_due
+    }
+}
@@ -1,3 +1,5 @@
 mod create_sheep;
+mod world;
 
 pub use create_sheep::{CreateSheepCommand, CreateSheepCommandQueue};
+pub use world::{WorldCommand, WorldCommandQueue};
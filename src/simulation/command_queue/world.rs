@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+/// Commands that operate on the whole world. These are executed between
+/// frames rather than by systems.
+#[derive(Clone, Debug)]
+pub enum WorldCommand {
+    /// Write the world to the save file at the path.
+    Save(PathBuf),
+
+    /// Replace the world with the contents of the save file at the path.
+    Load(PathBuf),
+
+    /// Replace the world with the scenario in the file at the path.
+    LoadScenario(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct WorldCommandQueue {
+    /// Commands with the index in the inbox of the message that requested
+    /// them.
+    pub commands: Vec<(usize, WorldCommand)>,
+}
+
+impl WorldCommandQueue {
+    pub fn new() -> WorldCommandQueue {
+        WorldCommandQueue { commands: vec![] }
+    }
+
+    pub fn push(&mut self, index: usize, command: WorldCommand) {
+        self.commands.push((index, command));
+    }
+}
@@ -1,4 +1,5 @@
 use nalgebra::{Rotation2, Vector2};
+use serde::{Deserialize, Serialize};
 use specs::{prelude::*, Component};
 use specs_derive::Component;
 use std::net::SocketAddr;
@@ -15,7 +16,7 @@ impl Socket {
 }
 
 /// Position in meters.
-#[derive(Clone, Copy, Component, Debug)]
+#[derive(Clone, Copy, Component, Debug, Deserialize, Serialize)]
 pub struct Position {
     pub v: Vector2<f32>,
 }
@@ -29,7 +30,7 @@ impl Position {
 }
 
 /// Heading rotation.
-#[derive(Clone, Copy, Component, Debug)]
+#[derive(Clone, Copy, Component, Debug, Deserialize, Serialize)]
 pub struct Heading {
     pub r: Rotation2<f32>,
 }
@@ -43,7 +44,7 @@ impl Heading {
 }
 
 /// Velocity in meters per second.
-#[derive(Clone, Copy, Component, Debug)]
+#[derive(Clone, Copy, Component, Debug, Deserialize, Serialize)]
 pub struct Velocity {
     pub v: Vector2<f32>,
 }
@@ -57,15 +58,26 @@ impl Velocity {
 }
 
 /// Types of sheep behavior.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum SheepBehavior {
     Stationary,
     Walking,
     Running,
 }
 
+impl SheepBehavior {
+    /// Gets the name of the behavior in snake case.
+    pub fn name(self) -> &'static str {
+        match self {
+            SheepBehavior::Stationary => "stationary",
+            SheepBehavior::Walking => "walking",
+            SheepBehavior::Running => "running",
+        }
+    }
+}
+
 /// Sheep behavior state.
-#[derive(Clone, Copy, Component, Debug)]
+#[derive(Clone, Copy, Component, Debug, Deserialize, Serialize)]
 pub struct SheepBehaviorState {
     pub behavior: SheepBehavior,
 }
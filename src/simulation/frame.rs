@@ -19,35 +19,57 @@ pub struct Frame {
     pub number: u64,
 
     /// The time at which the frame started.
+    #[allow(dead_code)]
     pub start_time: Instant,
 
     /// The ideal time at which the frame should have started. This is used to
     /// make sure sure that the start time of future frames don't cummulatively
     /// drift from the ideal start times.
     pub ideal_start_time: Instant,
+
+    /// Duration of a fixed length frame.
+    pub duration: Duration,
 }
 
 impl Frame {
-    /// Duration of a fixed length frame in milliseconds.
-    pub const DURATION_MILLIS: u64 = 32u64; // 16 ms = 62.5 FPS
+    /// Default duration of a fixed length frame in milliseconds.
+    pub const DEFAULT_DURATION_MILLIS: u64 = 32u64; // 16 ms = 62.5 FPS
+
+    pub fn new(duration: Duration) -> Frame {
+        Frame::starting_at(0, duration)
+    }
 
-    pub fn new() -> Frame {
+    /// Creates a frame counter that resumes counting from the given frame
+    /// number.
+    pub fn starting_at(number: u64, duration: Duration) -> Frame {
         let now = Instant::now();
         Frame {
-            number: 0,
+            number,
             start_time: now,
             ideal_start_time: now,
+            duration,
         }
     }
 
     pub fn next(&self, now: Instant) -> Frame {
         let elapsed_frame_count =
-            (now - self.ideal_start_time).as_millis() as u64 / Frame::DURATION_MILLIS;
+            ((now - self.ideal_start_time).as_nanos() / self.duration.as_nanos()) as u32;
         Frame {
-            number: self.number + elapsed_frame_count,
+            number: self.number + elapsed_frame_count as u64,
             start_time: now,
-            ideal_start_time: self.ideal_start_time
-                + Duration::from_millis(elapsed_frame_count * Frame::DURATION_MILLIS),
+            ideal_start_time: self.ideal_start_time + self.duration * elapsed_frame_count,
+            duration: self.duration,
+        }
+    }
+
+    /// Gets the frame immediately following this one without waiting for its
+    /// ideal start time.
+    pub fn advance(&self) -> Frame {
+        Frame {
+            number: self.number + 1,
+            start_time: Instant::now(),
+            ideal_start_time: self.ideal_start_time + self.duration,
+            duration: self.duration,
         }
     }
 }
@@ -18,7 +18,7 @@ impl<T: Clone + Copy> CellBlockBuilder<T> {
     pub fn finish(&self) -> CellBlock<T> {
         CellBlock {
             width: self.width,
-            cells: vec![self.default; (self.width * self.height) as usize],
+            cells: vec![self.default; self.width * self.height],
         }
     }
 }
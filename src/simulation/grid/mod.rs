@@ -21,7 +21,7 @@ pub trait Grid {
         pos: (usize, usize),
         max_dist: usize,
         predicate: P,
-    ) -> VisibleNeighborSearch<Self, P>
+    ) -> VisibleNeighborSearch<'_, Self, P>
     where
         Self: Sized,
         P: FnMut((usize, usize), &Self::Cell) -> bool,
@@ -38,7 +38,7 @@ pub trait Grid {
         pos: (usize, usize),
         max_dist: usize,
         predicate: P,
-    ) -> NeighborSearch<Self, P>
+    ) -> NeighborSearch<'_, Self, P>
     where
         Self: Sized,
         P: FnMut((usize, usize), &Self::Cell) -> bool,
@@ -83,7 +83,7 @@ impl<G, P> VisibleNeighborSearch<'_, G, P> {
         center: (usize, usize),
         max_dist: usize,
         predicate: P,
-    ) -> VisibleNeighborSearch<G, P> {
+    ) -> VisibleNeighborSearch<'_, G, P> {
         VisibleNeighborSearch {
             grid,
             center,
@@ -157,7 +157,7 @@ impl<G, P> NeighborSearch<'_, G, P> {
         center: (usize, usize),
         max_dist: usize,
         predicate: P,
-    ) -> NeighborSearch<G, P> {
+    ) -> NeighborSearch<'_, G, P> {
         NeighborSearch {
             grid,
             center,
@@ -1,68 +1,152 @@
+mod analytics;
+mod broadcast;
 mod command_queue;
 mod component;
 mod frame;
+// The neighbor searches are not used by any systems yet.
+#[allow(dead_code)]
 mod grid;
+mod options;
+mod params;
+mod path;
+mod playback;
+mod profile;
+mod recording;
+mod rejections;
+mod rng;
+mod room;
+mod run_state;
+mod save;
+mod scenario;
 mod snapshot;
 mod state;
+mod sweep;
 mod system;
+mod timing;
+mod trajectory;
+mod world_map;
 
+pub use options::Options;
+pub use path::Directories;
+pub use playback::replay;
+pub use room::run;
+pub use scenario::{names_in as scenario_names, SpawnGroup};
+pub use sweep::sweep;
+
+use crate::metrics::Metrics;
 use crate::network;
 use crate::network::channel;
-use frame::{DeltaFrame, Frame};
-use futures_channel::mpsc::unbounded;
+use crate::shutdown::Shutdown;
+use frame::Frame;
+use futures_channel::mpsc::UnboundedReceiver;
 use futures_util::{future, pin_mut, stream::StreamExt};
-use specs::prelude::*;
+use profile::Profile;
 use state::State;
 use std::{
     sync::{Arc, Mutex},
-    time::{Duration, Instant},
+    time::Instant,
 };
-use tokio::time::delay_for;
+use tokio::{task::yield_now, time::delay_for};
+use tracing::{debug_span, info};
 
-/// Runs a single step of the simulation.
+/// Runs a single step of the simulation and records its measurements in the
+/// room's metrics.
 async fn step(
     state: &mut State<'_, '_>,
     inbox_buffer: Arc<Mutex<Vec<network::IncomingMessage>>>,
     senders: Arc<Mutex<channel::SenderManager>>,
+    metrics: &Metrics,
+    room: &str,
 ) -> Result<(), String> {
     // Update the frame counter.
-    if let Some(frame) = state.frame {
-        // Wait until it's time for the next frame to start.
-        let frame_duration = Duration::from_millis(Frame::DURATION_MILLIS);
-        let duration_since_prev_ideal = Instant::now() - frame.ideal_start_time;
-        if duration_since_prev_ideal < frame_duration {
-            delay_for(frame_duration - duration_since_prev_ideal).await;
+    let next_frame = match state.frame {
+        Some(frame) if state.realtime => {
+            // Wait until it's time for the next frame to start.
+            let duration_since_prev_ideal = Instant::now() - frame.ideal_start_time;
+            if duration_since_prev_ideal < frame.duration {
+                delay_for(frame.duration - duration_since_prev_ideal).await;
+            } else {
+                // The simulation is behind, but messages must still be
+                // received, such as requests for the profile.
+                let () = yield_now().await;
+            }
+            frame.next(Instant::now())
+        }
+        Some(frame) => {
+            // Nothing else gets a chance to run between frames unless the
+            // simulation yields.
+            let () = yield_now().await;
+            frame.advance()
         }
+        None => Frame::new(state.frame_duration),
+    };
+    let dropped_frames = state.start_frame(next_frame).saturating_sub(1);
 
-        let next_frame = frame.next(Instant::now());
-        let delta = next_frame.number - frame.number;
-        state.frame = Some(next_frame);
-        state.world.insert(DeltaFrame::new(delta));
-    } else {
-        state.frame = Some(Frame::new());
-        state.world.insert(DeltaFrame::new(0));
-    }
+    // Everything that happens during the frame is logged within its span.
+    let frame_number = state.frame.map_or(0, |f| f.number);
+    let span = debug_span!("frame", number = frame_number);
+    let _enter = span.enter();
+    state.timings.set_frame_span(span.clone());
 
-    {
-        // Forward incoming messaging from the inbox buffer into
+    let inbox_len = {
+        // Forward incoming messaging from the inbox buffer into the inbox,
+        // replacing the messages handled during the previous frame.
         let mut inbox_buffer = inbox_buffer.lock().unwrap();
         let mut inbox = state.world.fetch_mut::<Vec<network::IncomingMessage>>();
-        while let Some(msg) = inbox_buffer.pop() {
-            inbox.push(msg);
-        }
-    }
+        inbox.clear();
+        inbox.extend(inbox_buffer.drain(..));
+        inbox.len()
+    };
 
-    // Execute a frame of the simulation.
-    state.dispatcher.dispatch(&state.world);
-    state.world.maintain();
+    let start = Instant::now();
+    state.run_frame();
+    let frame_duration = start.elapsed();
 
     // Send all outgoing messages generated during the frame on the appropriate
     // client channels.
-    let senders = senders.lock().unwrap();
-    let mut outbox = state.world.fetch_mut::<Vec<network::OutgoingMessage>>();
-    while let Some(msg) = outbox.pop() {
-        senders.send_to_client(msg);
-    }
+    let outbox_len = {
+        let senders = senders.lock().unwrap();
+        let mut outbox = state.world.fetch_mut::<Vec<network::OutgoingMessage>>();
+        let mut broadcasts = state.world.fetch_mut::<Vec<network::Broadcast>>();
+        let outbox_len = outbox.len() + broadcasts.len();
+        while let Some(msg) = outbox.pop() {
+            senders.send_to_client(msg);
+        }
+        for broadcast in broadcasts.drain(..) {
+            senders.broadcast(broadcast);
+        }
+        outbox_len
+    };
+
+    // Aggregate the time taken by each system, warning about frames that
+    // delay the next one.
+    let system_timings = state.timings.take();
+    let (budget, overran) = {
+        let mut profile = state.world.fetch_mut::<Profile>();
+        let overran = profile.record(frame_number, frame_duration, system_timings.clone());
+        if overran && state.realtime {
+            profile.warn_overrun();
+        }
+        (profile.budget(), overran)
+    };
+
+    let entities = state.count_entities();
+    metrics.update_room(room, |metrics| {
+        metrics.frame_duration.observe(frame_duration);
+        for (system, duration) in system_timings {
+            metrics
+                .system_durations
+                .entry(system)
+                .or_default()
+                .observe(duration);
+        }
+        metrics.dropped_frames += dropped_frames;
+        metrics.frame_budget = budget;
+        metrics.frame_overruns += u64::from(overran);
+        metrics.entities = entities;
+        metrics.inbox_len = inbox_len;
+        metrics.outbox_len = outbox_len;
+    });
 
     Ok(())
 }
@@ -75,26 +159,75 @@ async fn push_to_inbox_buffer(
     inbox_buffer.lock().unwrap().push(msg);
 }
 
-/// Runs the simulation.
-pub async fn run(senders: Arc<Mutex<channel::SenderManager>>) -> Result<(), String> {
-    // Insert the sender part of the simulation's channel into the sender
-    // manager.
-    let (sender, receiver) = unbounded();
-    senders.lock().unwrap().insert_sim_sender(sender);
+/// Runs the simulation of a room with the given options. The world is
+/// initialized from the scenario file if one is given, or from the default
+/// scenario otherwise. If a save file is given then the world is loaded from it
+/// before the first frame. The simulation runs until the room's channel is
+/// closed, the server shuts down, or the frame limit is reached.
+async fn run_room(
+    room: &str,
+    senders: Arc<Mutex<channel::SenderManager>>,
+    receiver: UnboundedReceiver<network::IncomingMessage>,
+    options: Options,
+    metrics: Arc<Metrics>,
+    mut shutdown: Shutdown,
+) -> Result<(), String> {
+    let scenario = match &options.scenario_file {
+        Some(path) => scenario::read(path)
+            .map_err(|err| format!("Unable to load scenario {}: {}", path.display(), err))?,
+        None => scenario::Scenario::default(),
+    };
 
-    // Push messages recieved on the simulation's channel into an inbox buffer.
+    // Push messages recieved on the room's channel into an inbox buffer.
     // The simulation loop will forward messages from the inbox buffer into the
     // inbox ECS resource between frames.
     let inbox_buffer = Arc::new(Mutex::new(vec![]));
     let handle_receiver = receiver.for_each(|msg| push_to_inbox_buffer(inbox_buffer.clone(), msg));
 
-    // Run the simulation loop.
-    let mut state = State::new();
-    let sim_loop = async {
-        while let Ok(()) = step(&mut state, inbox_buffer.clone(), senders.clone()).await {}
-    };
+    let mut state = State::new(&scenario, &options);
+    info!("Loaded scenario {}", scenario.name);
+    if let Some(path) = &options.save_file {
+        state
+            .load(path)
+            .map_err(|err| format!("Unable to load world from {}: {}", path.display(), err))?;
+        info!("Loaded world from {}", path.display());
+    }
+    state.open_outputs(&options)?;
+
+    // Run the simulation loop until it fails, the frame limit is reached, or
+    // the server shuts down.
+    {
+        let sim_loop = async {
+            while let Ok(()) = step(
+                &mut state,
+                inbox_buffer.clone(),
+                senders.clone(),
+                &metrics,
+                room,
+            )
+            .await
+            {
+                let frame_count = state.frame.map_or(0, |f| f.number + 1);
+                if options.max_frames.is_some_and(|max| frame_count >= max) {
+                    info!("Stopped after {} frames", frame_count);
+                    break;
+                }
+            }
+        };
+
+        let shutdown = shutdown.wait();
+        pin_mut!(handle_receiver, sim_loop, shutdown);
+        future::select(future::select(handle_receiver, sim_loop), shutdown).await;
+    }
+    metrics.remove_room(room);
+
+    if let Some(path) = &options.exit_save_file {
+        state
+            .save(path)
+            .map_err(|err| format!("Unable to save world to {}: {}", path.display(), err))?;
+        info!("Saved world to {}", path.display());
+    }
 
-    pin_mut!(handle_receiver, sim_loop);
-    future::select(handle_receiver, sim_loop).await;
+    state.close_outputs();
     Ok(())
 }
@@ -0,0 +1,78 @@
+use super::frame::Frame;
+use super::path::Directories;
+use std::{path::PathBuf, time::Duration};
+
+/// Options that control how the simulation is run.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Scenario file used to initialize the world. The default scenario is
+    /// used if no file is given.
+    pub scenario_file: Option<PathBuf>,
+
+    /// Save file from which the world is loaded before the first frame.
+    pub save_file: Option<PathBuf>,
+
+    /// Save file to which the world is written when the simulation stops.
+    pub exit_save_file: Option<PathBuf>,
+
+    /// Recording file to which every frame is written, starting with the first
+    /// frame.
+    pub record_file: Option<PathBuf>,
+
+    /// CSV file to which the statistics of the flock are written every frame.
+    pub analytics_file: Option<PathBuf>,
+
+    /// CSV file to which the state of every agent is written every
+    /// `trajectory_interval` frames.
+    pub trajectory_file: Option<PathBuf>,
+    pub trajectory_interval: u64,
+
+    /// Sheep that are at most this many meters apart are in the same cluster.
+    /// The cell size of the world map is used if no distance is given.
+    pub cluster_distance: Option<f32>,
+
+    /// Seed for the random number generator. The generator is seeded randomly
+    /// if no seed is given.
+    pub seed: Option<u64>,
+
+    /// Duration of a fixed length frame.
+    pub frame_duration: Duration,
+
+    /// Number of frames between broadcasts of the world state to clients.
+    pub broadcast_interval: u64,
+
+    /// If true then each frame waits until its ideal start time. Otherwise
+    /// frames are simulated as quickly as possible.
+    pub realtime: bool,
+
+    /// Number of frames after which the simulation stops.
+    pub max_frames: Option<u64>,
+
+    /// If true then diagnostics about each frame are logged at the debug and
+    /// trace levels.
+    pub diagnostics: bool,
+
+    pub dirs: Directories,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            scenario_file: None,
+            save_file: None,
+            exit_save_file: None,
+            record_file: None,
+            analytics_file: None,
+            trajectory_file: None,
+            trajectory_interval: 1,
+            cluster_distance: None,
+            seed: None,
+            frame_duration: Duration::from_millis(Frame::DEFAULT_DURATION_MILLIS),
+            broadcast_interval: 1,
+            realtime: true,
+            max_frames: None,
+            diagnostics: false,
+            dirs: Directories::default(),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Parameters of the sheep behavior model.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelParams {
+    /// Speed of walking sheep in meters per second.
+    pub walking_speed: f32,
+
+    /// Speed of running sheep in meters per second.
+    pub running_speed: f32,
+
+    /// Maximum angle in radians of the random rotation that is added to the
+    /// heading of a walking sheep each frame.
+    pub noise: f32,
+
+    /// Minimum length of the sum of the headings of the sheep in a cell for
+    /// the walking sheep in it to turn toward their mean heading. Below it
+    /// walking sheep keep their own heading. It must be positive, since the
+    /// direction of a sum of headings that cancel out is only rounding error.
+    pub alignment_threshold: f32,
+}
+
+impl Default for ModelParams {
+    fn default() -> ModelParams {
+        ModelParams {
+            walking_speed: 0.15,
+            running_speed: 1.5,
+            noise: 0.4082, // PI * 0.13
+            alignment_threshold: 0.1,
+        }
+    }
+}
+
+impl ModelParams {
+    /// Gets a copy of the parameters with the named parameters replaced by the
+    /// given values. Fails if a name or value is invalid.
+    pub fn with_changes(&self, changes: &Map<String, Value>) -> serde_json::Result<ModelParams> {
+        let mut fields = match serde_json::to_value(self)? {
+            Value::Object(fields) => fields,
+            _ => unreachable!("Parameters are serialized as an object."),
+        };
+        for (name, value) in changes {
+            fields.insert(name.clone(), value.clone());
+        }
+        let params: ModelParams = serde_json::from_value(Value::Object(fields))?;
+        params.validate().map_err(serde::de::Error::custom)?;
+        Ok(params)
+    }
+
+    /// Checks that every parameter is a finite number that isn't negative,
+    /// and that the alignment threshold is positive.
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [
+            ("walking_speed", self.walking_speed),
+            ("running_speed", self.running_speed),
+            ("noise", self.noise),
+            ("alignment_threshold", self.alignment_threshold),
+        ];
+        for (name, value) in &values {
+            if !value.is_finite() {
+                return Err(format!("{} must be a finite number, not {}", name, value));
+            }
+            if *value < 0.0 {
+                return Err(format!("{} must not be negative, not {}", name, value));
+            }
+        }
+        if self.alignment_threshold == 0.0 {
+            return Err("alignment_threshold must be positive, not 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModelParams;
+    use serde_json::json;
+
+    #[test]
+    fn changes_are_validated() {
+        let params = ModelParams::default();
+        let change = |value| match json!({ "noise": value }) {
+            serde_json::Value::Object(changes) => params.with_changes(&changes),
+            _ => unreachable!(),
+        };
+        assert_eq!(change(json!(0.2)).unwrap().noise, 0.2);
+        assert_eq!(
+            change(json!(0.2)).unwrap().walking_speed,
+            params.walking_speed
+        );
+        assert!(change(json!(-0.2)).is_err());
+        assert!(change(json!("loud")).is_err());
+    }
+
+    #[test]
+    fn non_finite_values_are_rejected() {
+        for value in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let params = ModelParams {
+                walking_speed: *value,
+                ..ModelParams::default()
+            };
+            let err = params.validate().unwrap_err();
+            assert!(err.contains("must be a finite number"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn alignment_threshold_must_be_positive() {
+        let params = |alignment_threshold| ModelParams {
+            alignment_threshold,
+            ..ModelParams::default()
+        };
+        assert!(params(0.0).validate().is_err());
+        assert!(params(-0.1).validate().is_err());
+        assert!(params(2.0).validate().is_ok());
+    }
+}
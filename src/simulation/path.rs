@@ -0,0 +1,36 @@
+use super::{recording, save, scenario};
+use std::path::{Path, PathBuf};
+
+/// Gets the path of the file with the given name and extension in the
+/// directory. Returns `None` if the name is empty or contains anything other
+/// than ASCII letters, digits, dashes, and underscores, so that clients cannot
+/// read or write files outside of the directory.
+pub fn file_in(dir: &Path, name: &str, extension: &str) -> Option<PathBuf> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_valid {
+        Some(dir.join(format!("{}.{}", name, extension)))
+    } else {
+        None
+    }
+}
+
+/// Directories in which files requested by clients are read and written.
+#[derive(Clone, Debug)]
+pub struct Directories {
+    pub saves: PathBuf,
+    pub recordings: PathBuf,
+    pub scenarios: PathBuf,
+}
+
+impl Default for Directories {
+    fn default() -> Directories {
+        Directories {
+            saves: PathBuf::from(save::DEFAULT_DIR),
+            recordings: PathBuf::from(recording::DEFAULT_DIR),
+            scenarios: PathBuf::from(scenario::DEFAULT_DIR),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use super::push_to_inbox_buffer;
+use super::recording::{self, RecordedFrame, Recording};
+use crate::network::{
+    self, channel, ClientCommand, IncomingMessage, OutgoingMessage, Role, Welcome, PROTOCOL_VERSION,
+};
+use crate::shutdown::Shutdown;
+use futures_channel::mpsc::unbounded;
+use futures_util::{future, pin_mut, stream::StreamExt};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::info;
+
+/// Plays back a recording at an adjustable speed.
+struct Player {
+    recording: Recording,
+
+    /// The frame number at the current playback position. This is fractional
+    /// so that playback can run slower than real time.
+    position: f64,
+
+    paused: bool,
+
+    /// Playback speed as a multiple of real time.
+    speed: f64,
+
+    /// Index of the recorded frame that was most recently sent to clients.
+    sent_idx: Option<usize>,
+}
+
+impl Player {
+    fn new(recording: Recording) -> Player {
+        let position = recording.frames.first().map_or(0, |f| f.frame) as f64;
+        Player {
+            recording,
+            position,
+            paused: false,
+            speed: 1.0,
+            sent_idx: None,
+        }
+    }
+
+    /// Handles a playback command. Fails if the command can't be used during
+    /// playback.
+    fn handle_command(&mut self, cmd: &ClientCommand) -> Result<(), String> {
+        match *cmd {
+            ClientCommand::Pause => self.paused = true,
+            ClientCommand::Resume => self.paused = false,
+            ClientCommand::Seek { frame } => {
+                self.position = frame as f64;
+                // Resend the frame at the new position even if it's the one
+                // that was sent most recently.
+                self.sent_idx = None;
+            }
+            ClientCommand::SetSpeed { speed } if speed.is_finite() && speed > 0.0 => {
+                self.speed = speed as f64;
+            }
+            ClientCommand::SetSpeed { speed } => {
+                return Err(format!("Speed must be positive, not {}", speed));
+            }
+            _ => return Err("Only playback commands can be used during replay".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Gets the welcome message for a client that connected, describing the
+    /// recorded world in the same way as the live simulation.
+    fn welcome(
+        &self,
+        sender: SocketAddr,
+        client_id: u64,
+        room: &str,
+        role: Role,
+    ) -> OutgoingMessage {
+        let world = &self.recording.header.world;
+        let welcome = Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            client_id,
+            room: room.to_string(),
+            role,
+            scenario: world.scenario.clone(),
+            bounds: world.bounds,
+            cell_size: world.cell_size,
+            grid_size: world.world_map().grid_size(),
+            frame_duration: self.recording.header.frame_millis as f64 / 1000.0,
+            frame: self.position as u64,
+        };
+        OutgoingMessage::welcome(sender, welcome)
+    }
+
+    /// Advances the playback position by one frame of real time. Playback
+    /// stops at the last recorded frame.
+    fn advance(&mut self) {
+        if self.paused {
+            return;
+        }
+        let last = self.recording.frames.last().map_or(0, |f| f.frame) as f64;
+        self.position = (self.position + self.speed).min(last);
+    }
+
+    /// Gets the recorded frame at the current playback position if it has not
+    /// been sent to clients yet.
+    fn next_unsent(&mut self) -> Option<&RecordedFrame> {
+        let idx = self.recording.index_at(self.position as u64)?;
+        if self.sent_idx == Some(idx) {
+            return None;
+        }
+        self.sent_idx = Some(idx);
+        self.recording.frames.get(idx)
+    }
+}
+
+/// Streams a recording to connected clients in place of running the
+/// simulation. Clients can pause, resume, seek, and change the playback speed.
+/// Playback continues until the server shuts down.
+pub async fn replay(
+    senders: Arc<Mutex<channel::SenderManager>>,
+    path: PathBuf,
+    mut shutdown: Shutdown,
+) -> Result<(), String> {
+    let recording = recording::read(&path)
+        .map_err(|err| format!("Unable to read recording {}: {}", path.display(), err))?;
+    info!(
+        "Replaying {} frames from {}",
+        recording.frames.len(),
+        path.display()
+    );
+
+    // Receive playback commands on the simulation's channel.
+    let (sender, receiver) = unbounded();
+    senders.lock().unwrap().insert_sim_sender(sender);
+    let inbox_buffer = Arc::new(Mutex::new(vec![]));
+    let handle_receiver = receiver.for_each(|msg| push_to_inbox_buffer(inbox_buffer.clone(), msg));
+
+    let frame_duration = Duration::from_millis(recording.header.frame_millis.max(1));
+    let mut player = Player::new(recording);
+    let playback_loop = async {
+        let mut interval = tokio::time::interval(frame_duration);
+        loop {
+            interval.tick().await;
+
+            for msg in inbox_buffer.lock().unwrap().drain(..) {
+                match msg {
+                    // Welcome the new client and resend the current frame so
+                    // that it doesn't have to wait for the next one.
+                    IncomingMessage::Connected {
+                        sender,
+                        client_id,
+                        room,
+                        role,
+                    } => {
+                        let welcome = player.welcome(sender, client_id, &room, role);
+                        senders.lock().unwrap().send_to_client(welcome);
+                        player.sent_idx = None;
+                    }
+                    IncomingMessage::Request { command, reply, .. } => {
+                        let _ = reply.send(player.handle_command(&command));
+                    }
+                    msg => {
+                        if let (Some(sender), Some(cmd)) = (msg.sender(), msg.command()) {
+                            if let Err(reason) = player.handle_command(cmd) {
+                                let error = OutgoingMessage::error(sender, reason);
+                                senders.lock().unwrap().send_to_client(error);
+                            }
+                        }
+                    }
+                }
+            }
+            player.advance();
+
+            if let Some(recorded_frame) = player.next_unsent() {
+                let senders = senders.lock().unwrap();
+                senders.broadcast(network::Broadcast {
+                    recipients: senders.client_addrs().copied().collect(),
+                    message: network::SharedMessage::world_state(
+                        recorded_frame.agent_states.clone(),
+                    ),
+                });
+            }
+        }
+    };
+
+    let shutdown = shutdown.wait();
+    pin_mut!(handle_receiver, playback_loop, shutdown);
+    future::select(future::select(handle_receiver, playback_loop), shutdown).await;
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use crate::network::{FrameProfile, ProfileReport, SystemProfile};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// Minimum time between warnings about frames that overran their budget.
+const WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time taken by each system of the dispatcher, aggregated over every frame
+/// since the simulation started.
+#[derive(Debug)]
+pub struct Profile {
+    /// Time that a frame may take without delaying the next one.
+    budget: Duration,
+
+    frames: u64,
+
+    /// Number of frames that took longer than the budget.
+    overruns: u64,
+
+    /// The most recently recorded frame.
+    last_frame: Option<LastFrame>,
+
+    systems: BTreeMap<&'static str, SystemStats>,
+
+    /// Time of the last overrun warning, and the number of overruns since.
+    last_warning: Option<Instant>,
+    unreported_overruns: u64,
+}
+
+#[derive(Debug)]
+struct LastFrame {
+    number: u64,
+    duration: Duration,
+
+    /// Time taken by each system that ran during the frame.
+    timings: Vec<(&'static str, Duration)>,
+}
+
+#[derive(Debug, Default)]
+struct SystemStats {
+    runs: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Profile {
+    pub fn new(budget: Duration) -> Profile {
+        Profile {
+            budget,
+            frames: 0,
+            overruns: 0,
+            last_frame: None,
+            systems: BTreeMap::new(),
+            last_warning: None,
+            unreported_overruns: 0,
+        }
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Records the time taken by a frame and by each system that ran during
+    /// it. Returns true if the frame overran its budget.
+    pub fn record(
+        &mut self,
+        frame: u64,
+        duration: Duration,
+        timings: Vec<(&'static str, Duration)>,
+    ) -> bool {
+        self.frames += 1;
+        for &(name, time) in &timings {
+            let stats = self.systems.entry(name).or_default();
+            stats.runs += 1;
+            stats.total += time;
+            stats.max = stats.max.max(time);
+        }
+        self.last_frame = Some(LastFrame {
+            number: frame,
+            duration,
+            timings,
+        });
+
+        let overran = duration > self.budget;
+        if overran {
+            self.overruns += 1;
+        }
+        overran
+    }
+
+    /// Logs a warning that the last frame overran its budget. Warnings are
+    /// logged at most once a second, counting the overruns in between.
+    pub fn warn_overrun(&mut self) {
+        let frame = match &self.last_frame {
+            Some(last_frame) => last_frame,
+            None => return,
+        };
+        self.unreported_overruns += 1;
+        let now = Instant::now();
+        if self
+            .last_warning
+            .is_some_and(|time| now - time < WARNING_INTERVAL)
+        {
+            return;
+        }
+
+        let slowest = frame.timings.iter().max_by_key(|(_, time)| *time);
+        warn!(
+            frame = frame.number,
+            overruns = self.unreported_overruns,
+            "Frame took {:?}, over its budget of {:?}. The slowest system was {}",
+            frame.duration,
+            self.budget,
+            slowest.map_or_else(
+                || "none".to_string(),
+                |(name, time)| format!("{} ({:?})", name, time)
+            )
+        );
+        self.last_warning = Some(now);
+        self.unreported_overruns = 0;
+    }
+
+    /// Summarizes the profile for clients. Systems are listed from the one
+    /// that has taken the most time to the one that has taken the least.
+    pub fn report(&self) -> ProfileReport {
+        let frame_time: Duration = self.systems.values().map(|stats| stats.total).sum();
+        let mut systems: Vec<(&&'static str, &SystemStats)> = self.systems.iter().collect();
+        systems.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total));
+
+        ProfileReport {
+            budget: self.budget.as_secs_f64(),
+            frames: self.frames,
+            overruns: self.overruns,
+            last_frame: self.last_frame.as_ref().map(|frame| FrameProfile {
+                number: frame.number,
+                duration: frame.duration.as_secs_f64(),
+                systems: frame
+                    .timings
+                    .iter()
+                    .map(|(name, time)| (name.to_string(), time.as_secs_f64()))
+                    .collect(),
+            }),
+            systems: systems
+                .into_iter()
+                .map(|(name, stats)| SystemProfile {
+                    name: name.to_string(),
+                    mean: stats.total.as_secs_f64() / stats.runs as f64,
+                    max: stats.max.as_secs_f64(),
+                    share: if frame_time.is_zero() {
+                        0.0
+                    } else {
+                        stats.total.as_secs_f64() / frame_time.as_secs_f64()
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profile;
+    use std::time::Duration;
+
+    #[test]
+    fn systems_are_reported_from_slowest_to_fastest() {
+        let ms = Duration::from_millis;
+        let mut profile = Profile::new(ms(10));
+        assert!(!profile.record(0, ms(4), vec![("fast", ms(1)), ("slow", ms(3))]));
+        assert!(profile.record(1, ms(12), vec![("fast", ms(1)), ("slow", ms(11))]));
+
+        let report = profile.report();
+        assert_eq!(report.frames, 2);
+        assert_eq!(report.overruns, 1);
+        assert_eq!(report.last_frame.unwrap().number, 1);
+        let names: Vec<&str> = report.systems.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["slow", "fast"]);
+        assert!((report.systems[0].mean - 0.007).abs() < 1e-9);
+        assert!((report.systems[0].max - 0.011).abs() < 1e-9);
+        assert!((report.systems[0].share - 14.0 / 16.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,41 @@
+use std::{convert::From, fmt, io};
+
+pub type RecordingResult<T> = Result<T, RecordingError>;
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    Serde(serde_json::Error),
+    InvalidName(String),
+    MissingHeader,
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            // Use the underlying implementations of `Display`.
+            RecordingError::Io(ref err) => write!(f, "IO error: {}", err),
+            RecordingError::Serde(ref err) => write!(f, "Serde error: {}", err),
+            RecordingError::InvalidName(ref name) => {
+                write!(f, "Invalid recording name: {:?}", name)
+            }
+            RecordingError::MissingHeader => write!(f, "Recording has no header"),
+            RecordingError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported recording version: {}", version)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> RecordingError {
+        RecordingError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for RecordingError {
+    fn from(err: serde_json::Error) -> RecordingError {
+        RecordingError::Serde(err)
+    }
+}
@@ -0,0 +1,230 @@
+mod error;
+
+pub use error::{RecordingError, RecordingResult};
+
+use super::path;
+use super::scenario::Scenario;
+use super::world_map::WorldMap;
+use crate::geometry::BoundingBox;
+use crate::network::AgentState;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Directory in which recordings requested by clients are written.
+pub const DEFAULT_DIR: &str = "recordings";
+
+/// Version of the recording format. Increment this whenever the layout of
+/// `RecordingHeader` or `RecordedFrame` changes.
+const VERSION: u32 = 1;
+
+/// The first line of a recording.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecordingHeader {
+    pub version: u32,
+
+    /// Duration of a frame in milliseconds when the recording was made.
+    pub frame_millis: u64,
+
+    /// The world that was recorded. Recordings made without it describe the
+    /// world of the default scenario.
+    #[serde(default)]
+    pub world: RecordedWorld,
+}
+
+/// Name of the scenario and layout of the world that was recorded, which are
+/// sent to clients that connect during playback.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedWorld {
+    pub scenario: String,
+
+    /// Area in which agents may move.
+    pub bounds: BoundingBox,
+
+    /// Width and height of a square grid cell in meters.
+    pub cell_size: f32,
+}
+
+impl RecordedWorld {
+    pub fn new(scenario: &str, map: &WorldMap) -> RecordedWorld {
+        RecordedWorld {
+            scenario: scenario.to_string(),
+            bounds: map.bounds,
+            cell_size: map.cell_size,
+        }
+    }
+
+    /// Gets the spatial layout of the recorded world. Obstacles aren't
+    /// recorded.
+    pub fn world_map(&self) -> WorldMap {
+        WorldMap {
+            bounds: self.bounds,
+            cell_size: self.cell_size,
+            obstacles: vec![],
+        }
+    }
+}
+
+impl Default for RecordedWorld {
+    fn default() -> RecordedWorld {
+        let scenario = Scenario::default();
+        RecordedWorld::new(&scenario.name, &scenario.world_map())
+    }
+}
+
+/// The state of every agent in a single frame. Each line after the header of a
+/// recording is a recorded frame.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedFrame {
+    pub frame: u64,
+    pub agent_states: Vec<AgentState>,
+}
+
+/// A recording that has been read into memory.
+#[derive(Debug)]
+pub struct Recording {
+    pub header: RecordingHeader,
+
+    /// Recorded frames in ascending frame number order.
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    /// Gets the index of the last recorded frame with a frame number that is
+    /// not greater than the given frame number.
+    pub fn index_at(&self, frame: u64) -> Option<usize> {
+        match self.frames.binary_search_by_key(&frame, |f| f.frame) {
+            Ok(idx) => Some(idx),
+            Err(0) => None,
+            Err(idx) => Some(idx - 1),
+        }
+    }
+}
+
+/// Writes frames to a recording file. Each frame is written as a single line
+/// of JSON.
+pub struct RecordingWriter {
+    writer: BufWriter<File>,
+}
+
+impl RecordingWriter {
+    /// Creates a recording file at the given path and writes its header.
+    pub fn create(
+        path: &Path,
+        frame_millis: u64,
+        world: RecordedWorld,
+    ) -> RecordingResult<RecordingWriter> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut recording_writer = RecordingWriter {
+            writer: BufWriter::new(File::create(path)?),
+        };
+        let header = RecordingHeader {
+            version: VERSION,
+            frame_millis,
+            world,
+        };
+        recording_writer.write_line(&header)?;
+        Ok(recording_writer)
+    }
+
+    pub fn write_frame(&mut self, frame: &RecordedFrame) -> RecordingResult<()> {
+        self.write_line(frame)
+    }
+
+    pub fn flush(&mut self) -> RecordingResult<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn write_line<T: Serialize>(&mut self, val: &T) -> RecordingResult<()> {
+        serde_json::to_writer(&mut self.writer, val)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Gets the path of the recording with the given name in the directory.
+pub fn path_in(dir: &Path, name: &str) -> RecordingResult<PathBuf> {
+    path::file_in(dir, name, "jsonl").ok_or_else(|| RecordingError::InvalidName(name.to_string()))
+}
+
+/// Reads the recording file at the given path.
+pub fn read(path: &Path) -> RecordingResult<Recording> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header: RecordingHeader = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(RecordingError::MissingHeader),
+    };
+    if header.version != VERSION {
+        return Err(RecordingError::UnsupportedVersion(header.version));
+    }
+
+    let mut frames: Vec<RecordedFrame> = vec![];
+    for line in lines {
+        let line = line?;
+        if !line.is_empty() {
+            frames.push(serde_json::from_str(&line)?);
+        }
+    }
+    frames.sort_by_key(|f| f.frame);
+
+    Ok(Recording { header, frames })
+}
+
+/// Resource holding the writer for the recording in progress, if any.
+#[derive(Default)]
+pub struct Recorder {
+    pub writer: Option<RecordingWriter>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecordedFrame, RecordedWorld, Recording, RecordingHeader};
+
+    fn recording(frame_numbers: &[u64]) -> Recording {
+        Recording {
+            header: RecordingHeader {
+                version: 1,
+                frame_millis: 32,
+                world: RecordedWorld::default(),
+            },
+            frames: frame_numbers
+                .iter()
+                .map(|&frame| RecordedFrame {
+                    frame,
+                    agent_states: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn index_at_exact_frame() {
+        assert_eq!(recording(&[2, 3, 5]).index_at(3), Some(1));
+    }
+
+    #[test]
+    fn index_at_skipped_frame() {
+        // Frame 4 was skipped so the most recent frame is frame 3.
+        assert_eq!(recording(&[2, 3, 5]).index_at(4), Some(1));
+        assert_eq!(recording(&[2, 3, 5]).index_at(9), Some(2));
+    }
+
+    #[test]
+    fn index_at_before_first_frame() {
+        assert_eq!(recording(&[2, 3, 5]).index_at(1), None);
+    }
+
+    #[test]
+    fn headers_without_world_describe_default_world() {
+        let header: RecordingHeader =
+            serde_json::from_str(r#"{"version": 1, "frame_millis": 32}"#).unwrap();
+        assert_eq!(header.world.scenario, "default");
+        assert_eq!(header.world.world_map().grid_size(), (16, 16));
+    }
+}
@@ -0,0 +1,21 @@
+use std::collections::BTreeMap;
+
+/// Reasons that the commands in the inbox were rejected, keyed by the index of
+/// their message in the inbox. At the end of the frame the clients that sent
+/// them are told why, and HTTP requests are answered with the reason.
+#[derive(Debug, Default)]
+pub struct Rejections {
+    reasons: BTreeMap<usize, String>,
+}
+
+impl Rejections {
+    /// Rejects the command in the message at the index of the inbox.
+    pub fn reject(&mut self, index: usize, reason: String) {
+        self.reasons.insert(index, reason);
+    }
+
+    /// Removes and returns every rejection.
+    pub fn take(&mut self) -> BTreeMap<usize, String> {
+        std::mem::take(&mut self.reasons)
+    }
+}
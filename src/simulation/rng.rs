@@ -0,0 +1,27 @@
+use rand::SeedableRng;
+use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
+
+/// Random number generator shared by all systems. Using a single seedable
+/// generator instead of `rand::thread_rng` makes it possible to save and
+/// restore the random state along with the rest of the world.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SimRng {
+    pub rng: Pcg64Mcg,
+}
+
+impl Default for SimRng {
+    fn default() -> SimRng {
+        SimRng {
+            rng: Pcg64Mcg::from_entropy(),
+        }
+    }
+}
+
+impl SimRng {
+    pub fn from_seed(seed: u64) -> SimRng {
+        SimRng {
+            rng: Pcg64Mcg::seed_from_u64(seed),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use super::{run_room, Options};
+use crate::metrics::Metrics;
+use crate::network::{
+    self, channel, ClientCommand, IncomingMessage, OutgoingMessage, Role, DEFAULT_ROOM,
+};
+use crate::shutdown::Shutdown;
+use futures_channel::{
+    mpsc::{unbounded, UnboundedSender},
+    oneshot,
+};
+use futures_util::{future, pin_mut, stream::StreamExt};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread,
+};
+use tokio::runtime;
+use tracing::{error, info, info_span, warn};
+
+/// A named simulation with its own world and tick loop running on a separate
+/// thread.
+struct Room {
+    /// The sender end of the room simulation's channel. The simulation stops
+    /// when this is dropped.
+    sender: UnboundedSender<IncomingMessage>,
+
+    /// Clients in the room.
+    members: HashSet<SocketAddr>,
+}
+
+/// The room that a connected client is in.
+struct Membership {
+    client_id: u64,
+    role: Role,
+    room: String,
+}
+
+/// Routes client events to the simulations of the rooms that the clients are
+/// in. Rooms are opened when the first client joins them and closed when the
+/// last client leaves, except for the default room which is open for as long
+/// as the server runs.
+struct RoomManager {
+    senders: Arc<Mutex<channel::SenderManager>>,
+    options: Options,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
+    rooms: HashMap<String, Room>,
+    clients: HashMap<SocketAddr, Membership>,
+}
+
+impl RoomManager {
+    /// Routes an event from a client connection.
+    fn handle(&mut self, msg: IncomingMessage) {
+        match msg {
+            IncomingMessage::Connected {
+                sender,
+                client_id,
+                room,
+                role,
+            } => self.join(sender, client_id, role, room),
+            IncomingMessage::Disconnected(sender) => self.leave(sender),
+            IncomingMessage::Message {
+                sender,
+                command: Some(ClientCommand::JoinRoom { name }),
+            } => {
+                if !network::is_valid_room_name(&name) {
+                    warn!("Rejected request from {}: invalid room name", sender);
+                    let error = OutgoingMessage::error(sender, "Invalid room name".to_string());
+                    self.senders.lock().unwrap().send_to_client(error);
+                    return;
+                }
+                if let Some(&Membership {
+                    client_id,
+                    role,
+                    ref room,
+                }) = self.clients.get(&sender)
+                {
+                    // Rejoining the same room would close and reopen it if the
+                    // client is its only member.
+                    if *room == name {
+                        return;
+                    }
+                    self.leave(sender);
+                    self.join(sender, client_id, role, name);
+                }
+            }
+            IncomingMessage::Query { ref room, .. } | IncomingMessage::Request { ref room, .. } => {
+                // The reply is dropped if the room isn't open.
+                if let Some(room) = self.rooms.get(room) {
+                    let _ = room.sender.unbounded_send(msg);
+                }
+            }
+            msg => {
+                if let Some(room) = msg.sender().and_then(|sender| self.room_of(&sender)) {
+                    let _ = room.sender.unbounded_send(msg);
+                }
+            }
+        }
+    }
+
+    /// Adds the client to the room, opening the room if needed.
+    fn join(&mut self, sender: SocketAddr, client_id: u64, role: Role, name: String) {
+        if !self.rooms.contains_key(&name) {
+            let (room, _) = self.open(&name, self.on_demand_options());
+            self.rooms.insert(name.clone(), room);
+        }
+
+        let room = self.rooms.get_mut(&name).expect("Room is open.");
+        room.members.insert(sender);
+        let _ = room.sender.unbounded_send(IncomingMessage::Connected {
+            sender,
+            client_id,
+            room: name.clone(),
+            role,
+        });
+        self.clients.insert(
+            sender,
+            Membership {
+                client_id,
+                role,
+                room: name,
+            },
+        );
+    }
+
+    /// Removes the client from its room, closing the room if it's empty.
+    fn leave(&mut self, sender: SocketAddr) {
+        let name = match self.clients.remove(&sender) {
+            Some(membership) => membership.room,
+            None => return,
+        };
+        if let Some(room) = self.rooms.get_mut(&name) {
+            let _ = room
+                .sender
+                .unbounded_send(IncomingMessage::Disconnected(sender));
+            room.members.remove(&sender);
+            if room.members.is_empty() && name != DEFAULT_ROOM {
+                self.rooms.remove(&name);
+                info!("Closed room {}", name);
+            }
+        }
+    }
+
+    /// Gets the options of a room that is opened on demand. Such rooms don't
+    /// read or write the files of the default room, which would otherwise be
+    /// truncated and written by two simulations at once.
+    fn on_demand_options(&self) -> Options {
+        Options {
+            save_file: None,
+            exit_save_file: None,
+            record_file: None,
+            analytics_file: None,
+            trajectory_file: None,
+            max_frames: None,
+            ..self.options.clone()
+        }
+    }
+
+    fn room_of(&self, sender: &SocketAddr) -> Option<&Room> {
+        let membership = self.clients.get(sender)?;
+        self.rooms.get(&membership.room)
+    }
+
+    /// Starts the simulation of a room on a new thread. The returned receiver
+    /// resolves when the simulation stops.
+    fn open(&self, name: &str, options: Options) -> (Room, oneshot::Receiver<Result<(), String>>) {
+        let (sender, receiver) = unbounded();
+        let (done_sender, done_receiver) = oneshot::channel();
+        let senders = self.senders.clone();
+        let metrics = self.metrics.clone();
+        let shutdown = self.shutdown.clone();
+        let span = info_span!("room", room = name);
+        let room_name = name.to_string();
+        thread::Builder::new()
+            .name(format!("room-{}", name))
+            .spawn(move || {
+                let _enter = span.enter();
+                let result = match runtime::Builder::new()
+                    .basic_scheduler()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(mut rt) => rt.block_on(run_room(
+                        &room_name, senders, receiver, options, metrics, shutdown,
+                    )),
+                    Err(err) => Err(format!("Unable to start runtime: {}", err)),
+                };
+                if let Err(err) = &result {
+                    error!("{}", err);
+                }
+                let _ = done_sender.send(result);
+            })
+            .expect("Unable to spawn room thread.");
+        info!("Opened room {}", name);
+
+        let room = Room {
+            sender,
+            members: HashSet::new(),
+        };
+        (room, done_receiver)
+    }
+}
+
+/// Runs the simulation rooms. The default room is opened immediately with the
+/// given options, while other rooms are opened on demand without loading or
+/// saving files. Runs until the default room's simulation stops.
+pub async fn run(
+    senders: Arc<Mutex<channel::SenderManager>>,
+    options: Options,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
+) -> Result<(), String> {
+    // Insert the sender part of the simulation's channel into the sender
+    // manager.
+    let (sender, receiver) = unbounded();
+    senders.lock().unwrap().insert_sim_sender(sender);
+
+    let mut manager = RoomManager {
+        senders,
+        options: options.clone(),
+        metrics,
+        shutdown,
+        rooms: HashMap::new(),
+        clients: HashMap::new(),
+    };
+    let (default_room, default_done) = manager.open(DEFAULT_ROOM, options);
+    manager.rooms.insert(DEFAULT_ROOM.to_string(), default_room);
+
+    let handle_receiver = receiver.for_each(|msg| {
+        manager.handle(msg);
+        future::ready(())
+    });
+    pin_mut!(handle_receiver);
+    match future::select(handle_receiver, default_done).await {
+        future::Either::Left(((), _)) => Ok(()),
+        future::Either::Right((Ok(result), _)) => result,
+        // The done channel is dropped without a result if the thread panics.
+        future::Either::Right((Err(oneshot::Canceled), _)) => {
+            Err(format!("The thread of room {} panicked", DEFAULT_ROOM))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options, RoomManager};
+    use crate::metrics::Metrics;
+    use crate::network::channel::SenderManager;
+    use crate::shutdown;
+    use crate::test_util::temp_path;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tokio::runtime;
+
+    #[test]
+    fn rooms_opened_on_demand_leave_the_default_room_files_alone() {
+        let dir = temp_path("rooms-opened-on-demand");
+        let (trigger, shutdown) = shutdown::channel();
+        let manager = RoomManager {
+            senders: Arc::new(Mutex::new(SenderManager::new())),
+            options: Options {
+                analytics_file: Some(dir.join("analytics.csv")),
+                trajectory_file: Some(dir.join("trajectories.csv")),
+                ..Options::default()
+            },
+            metrics: Arc::new(Metrics::default()),
+            shutdown,
+            rooms: HashMap::new(),
+            clients: HashMap::new(),
+        };
+
+        // The room's files are opened before its first frame, so they exist
+        // by the time it stops.
+        let (_room, done) = manager.open("arena", manager.on_demand_options());
+        trigger.fire();
+        let mut rt = runtime::Builder::new().basic_scheduler().build().unwrap();
+        assert_eq!(rt.block_on(done), Ok(Ok(())));
+        assert!(!dir.exists());
+    }
+}
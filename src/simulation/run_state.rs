@@ -0,0 +1,25 @@
+/// Whether the world is simulated from frame to frame. While the simulation
+/// is paused, messages and commands are still handled and the world state is
+/// still broadcast to clients.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunState {
+    pub paused: bool,
+
+    /// Number of frames to simulate while paused.
+    pub steps: u64,
+}
+
+impl RunState {
+    /// Returns true if the world should be simulated during the next frame.
+    /// Uses up one of the remaining steps if the simulation is paused.
+    pub fn advance(&mut self) -> bool {
+        if !self.paused {
+            true
+        } else if self.steps > 0 {
+            self.steps -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
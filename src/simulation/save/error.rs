@@ -0,0 +1,37 @@
+use std::{convert::From, fmt, io};
+
+pub type SaveResult<T> = Result<T, SaveError>;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Serde(serde_json::Error),
+    InvalidName(String),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            // Use the underlying implementations of `Display`.
+            SaveError::Io(ref err) => write!(f, "IO error: {}", err),
+            SaveError::Serde(ref err) => write!(f, "Serde error: {}", err),
+            SaveError::InvalidName(ref name) => write!(f, "Invalid save name: {:?}", name),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported save file version: {}", version)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> SaveError {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> SaveError {
+        SaveError::Serde(err)
+    }
+}
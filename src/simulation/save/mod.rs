@@ -0,0 +1,203 @@
+mod error;
+
+pub use error::{SaveError, SaveResult};
+
+use super::component::{Heading, Position, SheepBehaviorState, Velocity};
+use super::path;
+use super::rng::SimRng;
+use serde::{Deserialize, Serialize};
+use specs::{
+    error::NoError,
+    prelude::*,
+    saveload::{DeserializeComponents, SerializeComponents, SimpleMarker, SimpleMarkerAllocator},
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// Marks the entities that are written to save files.
+pub struct SaveMarker;
+
+pub type AgentMarker = SimpleMarker<SaveMarker>;
+
+pub type AgentMarkerAllocator = SimpleMarkerAllocator<SaveMarker>;
+
+/// Directory in which save files requested by clients are written.
+pub const DEFAULT_DIR: &str = "saves";
+
+/// Version of the save file format. Increment this whenever the saved
+/// components or the layout of `SaveFile` change.
+const VERSION: u32 = 1;
+
+/// Contents of a save file.
+#[derive(Deserialize, Serialize)]
+struct SaveFile {
+    version: u32,
+
+    /// The number of the frame at which the world was saved.
+    frame: u64,
+
+    /// State of the simulation's random number generator.
+    rng: SimRng,
+
+    /// Marked entities and their components, as serialized by specs.
+    agents: serde_json::Value,
+}
+
+/// Gets the path of the save file with the given name in the directory.
+pub fn path_in(dir: &Path, name: &str) -> SaveResult<PathBuf> {
+    path::file_in(dir, name, "json").ok_or_else(|| SaveError::InvalidName(name.to_string()))
+}
+
+/// Writes all marked entities and the state needed to resume the simulation
+/// to the file at the given path.
+pub fn write(world: &World, frame: u64, path: &Path) -> SaveResult<()> {
+    #[allow(clippy::type_complexity)]
+    let (entities, markers, pos_storage, heading_storage, vel_storage, behavior_storage) = world
+        .system_data::<(
+            Entities,
+            ReadStorage<AgentMarker>,
+            ReadStorage<Position>,
+            ReadStorage<Heading>,
+            ReadStorage<Velocity>,
+            ReadStorage<SheepBehaviorState>,
+        )>();
+
+    let agents = SerializeComponents::<NoError, AgentMarker>::serialize(
+        &(pos_storage, heading_storage, vel_storage, behavior_storage),
+        &entities,
+        &markers,
+        serde_json::value::Serializer,
+    )?;
+    let save_file = SaveFile {
+        version: VERSION,
+        frame,
+        rng: (*world.fetch::<SimRng>()).clone(),
+        agents,
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, &save_file)?;
+    Ok(())
+}
+
+/// Deletes all marked entities from the world.
+pub fn delete_marked(world: &mut World) {
+    {
+        let (entities, markers) = world.system_data::<(Entities, ReadStorage<AgentMarker>)>();
+        for (e, _) in (&entities, &markers).join() {
+            let _ = entities.delete(e);
+        }
+    }
+    world.maintain();
+}
+
+/// Replaces all marked entities in the world with the entities from the file at
+/// the given path. Returns the number of the frame at which the file was
+/// saved.
+pub fn read(world: &mut World, path: &Path) -> SaveResult<u64> {
+    let reader = BufReader::new(File::open(path)?);
+    let save_file: SaveFile = serde_json::from_reader(reader)?;
+    if save_file.version != VERSION {
+        return Err(SaveError::UnsupportedVersion(save_file.version));
+    }
+
+    delete_marked(world);
+    world.insert(AgentMarkerAllocator::new());
+
+    {
+        #[allow(clippy::type_complexity)]
+        let (
+            entities,
+            mut markers,
+            mut allocator,
+            pos_storage,
+            heading_storage,
+            vel_storage,
+            behavior_storage,
+        ) = world.system_data::<(
+            Entities,
+            WriteStorage<AgentMarker>,
+            WriteExpect<AgentMarkerAllocator>,
+            WriteStorage<Position>,
+            WriteStorage<Heading>,
+            WriteStorage<Velocity>,
+            WriteStorage<SheepBehaviorState>,
+        )>();
+
+        DeserializeComponents::<NoError, AgentMarker>::deserialize(
+            &mut (pos_storage, heading_storage, vel_storage, behavior_storage),
+            &entities,
+            &mut markers,
+            &mut allocator,
+            save_file.agents,
+        )?;
+    }
+    world.insert(save_file.rng);
+
+    Ok(save_file.frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{path_in, read, write, AgentMarker, AgentMarkerAllocator};
+    use crate::simulation::component::{
+        Heading, Position, SheepBehavior, SheepBehaviorState, Velocity,
+    };
+    use crate::simulation::rng::SimRng;
+    use crate::test_util::temp_path;
+    use specs::{prelude::*, saveload::MarkedBuilder};
+    use std::path::Path;
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Heading>();
+        world.register::<Velocity>();
+        world.register::<SheepBehaviorState>();
+        world.register::<AgentMarker>();
+        world.insert(AgentMarkerAllocator::new());
+        world.insert(SimRng::default());
+        world
+    }
+
+    #[test]
+    fn path_in_rejects_separators() {
+        assert!(path_in(Path::new("saves"), "example_1").is_ok());
+        assert!(path_in(Path::new("saves"), "../example").is_err());
+        assert!(path_in(Path::new("saves"), "").is_err());
+    }
+
+    #[test]
+    fn write_then_read() {
+        let mut saved = new_world();
+        saved
+            .create_entity()
+            .with(Position::new(1.0, 2.0))
+            .with(Heading::new(0.5))
+            .with(Velocity::new(0.1, 0.0))
+            .with(SheepBehaviorState::new(SheepBehavior::Running))
+            .marked::<AgentMarker>()
+            .build();
+        let path = temp_path("write-then-read.json");
+        write(&saved, 42, &path).unwrap();
+
+        let mut loaded = new_world();
+        let frame = read(&mut loaded, &path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(frame, 42);
+        let positions = loaded.read_storage::<Position>();
+        let behaviors = loaded.read_storage::<SheepBehaviorState>();
+        let agents: Vec<_> = (&positions, &behaviors).join().collect();
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].0.v.x, 1.0);
+        assert_eq!(agents[0].0.v.y, 2.0);
+        assert_eq!(agents[0].1.behavior, SheepBehavior::Running);
+    }
+}
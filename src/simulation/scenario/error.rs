@@ -0,0 +1,49 @@
+use std::{convert::From, fmt, io};
+
+pub type ScenarioResult<T> = Result<T, ScenarioError>;
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    Serde(serde_json::Error),
+    Toml(toml::de::Error),
+    InvalidName(String),
+    UnsupportedFormat(String),
+    Invalid(String),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            // Use the underlying implementations of `Display`.
+            ScenarioError::Io(ref err) => write!(f, "IO error: {}", err),
+            ScenarioError::Serde(ref err) => write!(f, "Serde error: {}", err),
+            ScenarioError::Toml(ref err) => write!(f, "TOML error: {}", err),
+            ScenarioError::InvalidName(ref name) => {
+                write!(f, "Invalid scenario name: {:?}", name)
+            }
+            ScenarioError::UnsupportedFormat(ref ext) => {
+                write!(f, "Unsupported scenario format: {:?}", ext)
+            }
+            ScenarioError::Invalid(ref reason) => write!(f, "Invalid scenario: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for ScenarioError {
+    fn from(err: io::Error) -> ScenarioError {
+        ScenarioError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ScenarioError {
+    fn from(err: serde_json::Error) -> ScenarioError {
+        ScenarioError::Serde(err)
+    }
+}
+
+impl From<toml::de::Error> for ScenarioError {
+    fn from(err: toml::de::Error) -> ScenarioError {
+        ScenarioError::Toml(err)
+    }
+}
@@ -0,0 +1,192 @@
+mod error;
+mod spawn;
+
+pub use error::{ScenarioError, ScenarioResult};
+pub use spawn::SpawnGroup;
+
+use super::component::SheepBehavior;
+use super::params::ModelParams;
+use super::path;
+use super::world_map::WorldMap;
+use crate::geometry::BoundingBox;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory from which clients can load scenarios.
+pub const DEFAULT_DIR: &str = "scenarios";
+
+/// Name of the scenario that the world was initialized from.
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioName(pub String);
+
+/// Description of the initial state of a simulation. Scenarios are read from
+/// TOML or JSON files.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+
+    /// Area in which sheep may move.
+    pub bounds: BoundingBox,
+
+    /// Width and height of a square grid cell in meters.
+    pub cell_size: f32,
+
+    #[serde(default)]
+    pub params: ModelParams,
+
+    /// Areas inside the bounds that sheep may not enter.
+    #[serde(default)]
+    pub obstacles: Vec<BoundingBox>,
+
+    /// Groups of sheep that are created when the scenario is loaded.
+    #[serde(default)]
+    pub spawns: Vec<SpawnGroup>,
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario {
+            name: "default".to_string(),
+            bounds: BoundingBox::new(0.0, 80.0, 0.0, 80.0),
+            cell_size: 5.0,
+            params: ModelParams::default(),
+            obstacles: vec![],
+            spawns: vec![SpawnGroup::Lattice {
+                origin: (3.0, 3.0),
+                spacing: 3.0,
+                columns: 5,
+                rows: 5,
+                behavior: SheepBehavior::Walking,
+                heading: Some(0.0),
+            }],
+        }
+    }
+}
+
+impl Scenario {
+    /// Gets the spatial layout of the scenario.
+    pub fn world_map(&self) -> WorldMap {
+        WorldMap {
+            bounds: self.bounds,
+            cell_size: self.cell_size,
+            obstacles: self.obstacles.clone(),
+        }
+    }
+
+    /// Checks that the scenario describes a world that can be simulated.
+    pub fn validate(&self) -> ScenarioResult<()> {
+        let b = &self.bounds;
+        if ![b.x_min, b.x_max, b.y_min, b.y_max]
+            .iter()
+            .all(|b| b.is_finite())
+        {
+            return Err(ScenarioError::Invalid(format!("infinite bounds {:?}", b)));
+        }
+        if !(b.x_min < b.x_max && b.y_min < b.y_max) {
+            return Err(ScenarioError::Invalid(format!("empty bounds {:?}", b)));
+        }
+        if self.cell_size.is_nan() || self.cell_size <= 0.0 {
+            return Err(ScenarioError::Invalid(format!(
+                "cell size must be positive, not {}",
+                self.cell_size
+            )));
+        }
+        self.params.validate().map_err(ScenarioError::Invalid)?;
+        for group in &self.spawns {
+            group.validate().map_err(ScenarioError::Invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the path of the scenario with the given name in the directory. TOML
+/// files take precedence over JSON files.
+pub fn path_in(dir: &Path, name: &str) -> ScenarioResult<PathBuf> {
+    let invalid_name = || ScenarioError::InvalidName(name.to_string());
+    let toml_path = path::file_in(dir, name, "toml").ok_or_else(invalid_name)?;
+    let json_path = path::file_in(dir, name, "json").ok_or_else(invalid_name)?;
+    if !toml_path.exists() && json_path.exists() {
+        Ok(json_path)
+    } else {
+        Ok(toml_path)
+    }
+}
+
+/// Gets the names of the scenarios in the directory in alphabetical order.
+pub fn names_in(dir: &Path) -> ScenarioResult<Vec<String>> {
+    let mut names = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_scenario = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("toml") | Some("json")
+        );
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            // Only list the scenarios that clients can load by name.
+            if is_scenario && path::file_in(dir, name, "toml").is_some() {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Reads and validates the scenario file at the given path. The format of the
+/// file is determined by its extension.
+pub fn read(path: &Path) -> ScenarioResult<Scenario> {
+    let contents = fs::read_to_string(path)?;
+    let scenario: Scenario = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents)?,
+        Some("json") => serde_json::from_str(&contents)?,
+        ext => {
+            return Err(ScenarioError::UnsupportedFormat(
+                ext.unwrap_or_default().to_string(),
+            ))
+        }
+    };
+    scenario.validate()?;
+    Ok(scenario)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scenario;
+    use crate::simulation::rng::SimRng;
+
+    #[test]
+    fn example_scenario() {
+        let scenario: Scenario =
+            toml::from_str(include_str!("../../../scenarios/example.toml")).unwrap();
+        assert!(scenario.validate().is_ok());
+
+        let map = scenario.world_map();
+        assert_eq!(map.grid_size(), (20, 12));
+
+        let mut rng = SimRng::default();
+        let counts: Vec<usize> = scenario
+            .spawns
+            .iter()
+            .map(|group| group.create_commands(&map, &mut rng).len())
+            .collect();
+        assert_eq!(counts, vec![25, 20, 15, 2]);
+    }
+
+    #[test]
+    fn spawns_outside_open_area_are_skipped() {
+        let mut scenario = Scenario::default();
+        scenario
+            .obstacles
+            .push(crate::geometry::BoundingBox::new(0.0, 7.0, 0.0, 80.0));
+        let commands =
+            scenario.spawns[0].create_commands(&scenario.world_map(), &mut SimRng::default());
+
+        // The first two columns of the 5x5 lattice are inside the obstacle.
+        assert_eq!(commands.len(), 15);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::geometry::BoundingBox;
+use crate::simulation::command_queue::CreateSheepCommand;
+use crate::simulation::component::{
+    Heading, Position, SheepBehavior, SheepBehaviorState, Velocity,
+};
+use crate::simulation::rng::SimRng;
+use crate::simulation::world_map::WorldMap;
+use nalgebra::Vector2;
+use rand::distributions::{Distribution, Uniform};
+use rand_distr::Normal;
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+/// Maximum number of times a random position is sampled while looking for an
+/// open position for a single sheep.
+const MAX_ATTEMPTS: usize = 100;
+
+/// Maximum number of sheep in a single group, so that a single command can't
+/// exhaust the memory of the server.
+pub const MAX_GROUP_SIZE: u64 = 100_000;
+
+/// A group of sheep that are created when a scenario is loaded. Groups are
+/// tables with a `kind` field naming the layout of the group.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SpawnGroup {
+    /// Sheep placed on a rectangular lattice.
+    Lattice {
+        /// Position of the bottom left sheep.
+        origin: (f32, f32),
+
+        /// Distance between neighboring sheep in meters.
+        spacing: f32,
+
+        columns: u32,
+        rows: u32,
+        behavior: SheepBehavior,
+
+        /// Initial heading in radians. Headings are random if not given.
+        heading: Option<f32>,
+    },
+
+    /// Sheep placed uniformly at random.
+    UniformRandom {
+        count: u32,
+
+        /// Area in which sheep are placed. Defaults to the world bounds.
+        area: Option<BoundingBox>,
+
+        behavior: SheepBehavior,
+        heading: Option<f32>,
+    },
+
+    /// Sheep placed in a normally distributed cluster.
+    GaussianCluster {
+        count: u32,
+        center: (f32, f32),
+
+        /// Standard deviation of the distance from the center along each axis.
+        std_dev: f32,
+
+        behavior: SheepBehavior,
+        heading: Option<f32>,
+    },
+
+    /// Sheep placed at explicitly listed positions.
+    Explicit { sheep: Vec<SheepSpec> },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SheepSpec {
+    pub position: (f32, f32),
+    pub behavior: SheepBehavior,
+    pub heading: Option<f32>,
+}
+
+impl SpawnGroup {
+    /// Checks that the parameters of the group can produce sheep.
+    pub fn validate(&self) -> Result<(), String> {
+        let size = match self {
+            SpawnGroup::Lattice { columns, rows, .. } => u64::from(*columns) * u64::from(*rows),
+            SpawnGroup::UniformRandom { count, .. } | SpawnGroup::GaussianCluster { count, .. } => {
+                u64::from(*count)
+            }
+            SpawnGroup::Explicit { sheep } => sheep.len() as u64,
+        };
+        if size > MAX_GROUP_SIZE {
+            return Err(format!(
+                "a group can't have more than {} sheep, not {}",
+                MAX_GROUP_SIZE, size
+            ));
+        }
+
+        match *self {
+            SpawnGroup::Lattice { spacing, .. } if !spacing.is_finite() || spacing <= 0.0 => {
+                Err(format!("lattice spacing must be positive, not {}", spacing))
+            }
+            SpawnGroup::UniformRandom {
+                area: Some(area), ..
+            } if !is_valid_area(&area) => Err(format!("invalid area {:?}", area)),
+            SpawnGroup::GaussianCluster { std_dev, .. }
+                if !std_dev.is_finite() || std_dev < 0.0 =>
+            {
+                Err(format!(
+                    "cluster standard deviation must not be negative, not {}",
+                    std_dev
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates commands for creating the sheep in the group. Sheep that would
+    /// be placed outside the world or inside an obstacle are skipped.
+    pub fn create_commands(&self, map: &WorldMap, rng: &mut SimRng) -> Vec<CreateSheepCommand> {
+        let mut cmds = vec![];
+        match self {
+            SpawnGroup::Lattice {
+                origin,
+                spacing,
+                columns,
+                rows,
+                behavior,
+                heading,
+            } => {
+                for col in 0..*columns {
+                    for row in 0..*rows {
+                        let pos = Vector2::new(
+                            origin.0 + col as f32 * spacing,
+                            origin.1 + row as f32 * spacing,
+                        );
+                        if map.is_open(pos) {
+                            cmds.push(new_command(pos, *behavior, *heading, rng));
+                        }
+                    }
+                }
+            }
+            SpawnGroup::UniformRandom {
+                count,
+                area,
+                behavior,
+                heading,
+            } => {
+                let area = area.unwrap_or(map.bounds);
+                let x_dist = Uniform::new_inclusive(area.x_min, area.x_max);
+                let y_dist = Uniform::new_inclusive(area.y_min, area.y_max);
+                for _ in 0..*count {
+                    let pos = sample_open(map, rng, |rng| {
+                        Vector2::new(x_dist.sample(&mut rng.rng), y_dist.sample(&mut rng.rng))
+                    });
+                    if let Some(pos) = pos {
+                        cmds.push(new_command(pos, *behavior, *heading, rng));
+                    }
+                }
+            }
+            SpawnGroup::GaussianCluster {
+                count,
+                center,
+                std_dev,
+                behavior,
+                heading,
+            } => {
+                let x_dist = Normal::new(center.0, *std_dev).expect("Invalid cluster.");
+                let y_dist = Normal::new(center.1, *std_dev).expect("Invalid cluster.");
+                for _ in 0..*count {
+                    let pos = sample_open(map, rng, |rng| {
+                        Vector2::new(x_dist.sample(&mut rng.rng), y_dist.sample(&mut rng.rng))
+                    });
+                    if let Some(pos) = pos {
+                        cmds.push(new_command(pos, *behavior, *heading, rng));
+                    }
+                }
+            }
+            SpawnGroup::Explicit { sheep } => {
+                for spec in sheep {
+                    let pos = Vector2::new(spec.position.0, spec.position.1);
+                    if map.is_open(pos) {
+                        cmds.push(new_command(pos, spec.behavior, spec.heading, rng));
+                    }
+                }
+            }
+        }
+        cmds
+    }
+}
+
+/// Returns true if sheep can be placed uniformly at random in the area. Areas
+/// may be lines or points but the bounds must be finite and in order.
+fn is_valid_area(area: &BoundingBox) -> bool {
+    let bounds = [area.x_min, area.x_max, area.y_min, area.y_max];
+    bounds.iter().all(|b| b.is_finite()) && area.x_min <= area.x_max && area.y_min <= area.y_max
+}
+
+/// Samples positions until one is open or the maximum number of attempts is
+/// reached.
+fn sample_open<F>(map: &WorldMap, rng: &mut SimRng, mut sample: F) -> Option<Vector2<f32>>
+where
+    F: FnMut(&mut SimRng) -> Vector2<f32>,
+{
+    (0..MAX_ATTEMPTS)
+        .map(|_| sample(rng))
+        .find(|&pos| map.is_open(pos))
+}
+
+fn new_command(
+    pos: Vector2<f32>,
+    behavior: SheepBehavior,
+    heading: Option<f32>,
+    rng: &mut SimRng,
+) -> CreateSheepCommand {
+    let heading = heading.unwrap_or_else(|| Uniform::new(-PI, PI).sample(&mut rng.rng));
+    CreateSheepCommand {
+        position: Position::new(pos.x, pos.y),
+        heading: Heading::new(heading),
+        velocity: Velocity::new(0.0, 0.0),
+        behavior: SheepBehaviorState::new(behavior),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpawnGroup;
+
+    fn group(text: &str) -> SpawnGroup {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn valid_groups_are_accepted() {
+        let uniform = "kind = 'uniform_random'\ncount = 10\nbehavior = 'Walking'\n";
+        assert!(group(uniform).validate().is_ok());
+        let area = "area = { x_min = 1.0, x_max = 1.0, y_min = 0.0, y_max = 5.0 }";
+        assert!(group(&format!("{}{}", uniform, area)).validate().is_ok());
+    }
+
+    #[test]
+    fn invalid_areas_are_rejected() {
+        let areas = [
+            "{ x_min = 5.0, x_max = 1.0, y_min = 0.0, y_max = 5.0 }",
+            "{ x_min = 0.0, x_max = 5.0, y_min = 5.0, y_max = 1.0 }",
+            "{ x_min = nan, x_max = 5.0, y_min = 0.0, y_max = 5.0 }",
+            "{ x_min = 0.0, x_max = inf, y_min = 0.0, y_max = 5.0 }",
+        ];
+        for area in &areas {
+            let text = format!(
+                "kind = 'uniform_random'\ncount = 10\nbehavior = 'Walking'\narea = {}",
+                area
+            );
+            assert!(group(&text).validate().is_err(), "{}", area);
+        }
+    }
+
+    #[test]
+    fn oversized_groups_are_rejected() {
+        let lattice = "kind = 'lattice'\norigin = [0.0, 0.0]\nspacing = 1.0\ncolumns = 100000\nrows = 100000\nbehavior = 'Walking'";
+        assert!(group(lattice).validate().is_err());
+        let uniform = "kind = 'uniform_random'\ncount = 4000000000\nbehavior = 'Walking'";
+        assert!(group(uniform).validate().is_err());
+        let cluster = "kind = 'gaussian_cluster'\ncount = 4000000000\ncenter = [0.0, 0.0]\nstd_dev = 1.0\nbehavior = 'Walking'";
+        assert!(group(cluster).validate().is_err());
+    }
+
+    #[test]
+    fn non_finite_distances_are_rejected() {
+        let lattice = "kind = 'lattice'\norigin = [0.0, 0.0]\nspacing = inf\ncolumns = 2\nrows = 2\nbehavior = 'Walking'";
+        assert!(group(lattice).validate().is_err());
+        let cluster = "kind = 'gaussian_cluster'\ncount = 2\ncenter = [0.0, 0.0]\nstd_dev = nan\nbehavior = 'Walking'";
+        assert!(group(cluster).validate().is_err());
+    }
+}
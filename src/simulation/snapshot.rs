@@ -29,6 +29,7 @@ impl AllSheepSnapshot {
 }
 
 /// Snapshot of information about running sheep in a cell.
+#[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub struct RunningSheepSnapshotCell {
     /// The number of running sheep in the cell.
@@ -47,6 +48,7 @@ impl Default for RunningSheepSnapshotCell {
     }
 }
 
+#[allow(dead_code)]
 pub struct RunningSheepSnapshot {
     pub grid: CellBlock<RunningSheepSnapshotCell>,
 }
@@ -1,25 +1,67 @@
 use super::{
-    command_queue::{CreateSheepCommand, CreateSheepCommandQueue},
+    analytics::{Analytics, AnalyticsWriter},
+    broadcast::BroadcastSchedule,
+    command_queue::{CreateSheepCommandQueue, WorldCommand, WorldCommandQueue},
     component,
-    frame::Frame,
-    network, snapshot, system,
+    frame::{DeltaFrame, Frame},
+    network,
+    options::Options,
+    params::ModelParams,
+    profile::Profile,
+    recording::{RecordedWorld, Recorder, RecordingWriter},
+    rejections::Rejections,
+    rng::SimRng,
+    run_state::RunState,
+    save::{self, AgentMarker, AgentMarkerAllocator, SaveResult},
+    scenario::{self, Scenario, ScenarioName},
+    snapshot, system,
+    timing::{SystemTimings, TimedDispatcherBuilder},
+    trajectory::{Trajectories, TrajectoryWriter},
+    world_map::WorldMap,
 };
+use serde::Serialize;
 use specs::prelude::*;
+use std::{collections::BTreeMap, path::Path, time::Duration};
+use tracing::{error, info, warn};
 
 pub struct State<'a, 'b> {
     pub world: World,
     pub dispatcher: Dispatcher<'a, 'b>,
+
+    /// Runs the systems that handle messages and commands while the
+    /// simulation is paused.
+    pub paused_dispatcher: Dispatcher<'a, 'b>,
+
+    /// Time taken by each system during the current frame.
+    pub timings: SystemTimings,
+
     pub frame: Option<Frame>,
+
+    /// Duration of a fixed length frame.
+    pub frame_duration: Duration,
+
+    /// If true then each frame waits until its ideal start time.
+    pub realtime: bool,
+}
+
+/// Summary of the world that is sent in reply to a state query.
+#[derive(Serialize)]
+struct WorldSummary<'a> {
+    scenario: &'a str,
+    frame: u64,
+    params: &'a ModelParams,
+    agent_states: Vec<network::AgentState>,
 }
 
 impl State<'_, '_> {
-    pub fn new() -> Self {
+    pub fn new(scenario: &Scenario, options: &Options) -> Self {
         // Register components.
         let mut world = World::new();
         world.register::<component::Position>();
         world.register::<component::Heading>();
         world.register::<component::Velocity>();
         world.register::<component::SheepBehaviorState>();
+        world.register::<AgentMarker>();
 
         // Set up dispatcher and systems.
         let mut dispatcher = DispatcherBuilder::new().build();
@@ -27,14 +69,40 @@ impl State<'_, '_> {
 
         // Initialize resources.
         State::initialize_mailboxes(&mut world);
-        State::initialize_cmd_queue(&mut world);
-        State::initialize_snapshots(&mut world);
+        State::initialize_cmd_queues(&mut world);
+        world.insert(options.seed.map_or_else(SimRng::default, SimRng::from_seed));
+        world.insert(options.dirs.clone());
+        world.insert(BroadcastSchedule::new(options.broadcast_interval));
+        world.insert(AgentMarkerAllocator::new());
+        world.insert(Recorder::default());
+        world.insert(RunState::default());
+        world.insert(Profile::new(options.frame_duration));
+        world.insert(Analytics {
+            cluster_distance: options.cluster_distance,
+            ..Analytics::default()
+        });
+        world.insert(Trajectories::default());
 
         // Set up dispatcher and systems.
-        let mut dispatcher = DispatcherBuilder::new()
-            .with(system::DebugLogSystem, "debug_log", &[])
+        let timings = SystemTimings::default();
+        let mut dispatcher = TimedDispatcherBuilder::new(timings.clone());
+        if options.diagnostics {
+            dispatcher = dispatcher.with(system::DiagnosticsSystem, "diagnostics", &[]);
+        }
+        let mut dispatcher = dispatcher
             // Process messages from inbox.
-            .with(system::CreateSocketSystem, "create_port", &["debug_log"])
+            .with(system::CreateSocketSystem, "create_port", &[])
+            .with(
+                system::DeleteSocketSystem,
+                "delete_socket",
+                &["create_port"],
+            )
+            .with(system::WelcomeSystem, "welcome", &["create_port"])
+            .with(system::WorldCommandSystem, "world_command", &[])
+            .with(system::ControlSystem, "control", &[])
+            .with(system::AgentCommandSystem, "agent_command", &[])
+            .with(system::RecordingCommandSystem, "recording_command", &[])
+            .with(system::ReportSystem, "report", &[])
             // Take snapshots.
             .with(
                 system::ResetAllSheepSnapshotSystem,
@@ -60,15 +128,322 @@ impl State<'_, '_> {
             .with(system::PositionSystem, "position", &["sheep_velocity"])
             // Send messages to outbox.
             .with(system::OutboxSystem, "outbox", &["position"])
+            .with(
+                system::RecordSystem,
+                "record",
+                &["position", "recording_command"],
+            )
+            .with(system::AnalyticsSystem, "analytics", &["position"])
+            .with(system::TrajectorySystem, "trajectory", &["position"])
             // Execute commands to create adnd delete entities.
-            .with(system::CreateCommandSystem, "create_command", &["outbox"])
+            .with(
+                system::CreateCommandSystem,
+                "create_command",
+                &[
+                    "outbox",
+                    "record",
+                    "analytics",
+                    "trajectory",
+                    "agent_command",
+                ],
+            )
             .build();
         dispatcher.setup(&mut world);
 
-        State {
+        let mut paused_dispatcher = TimedDispatcherBuilder::new(timings.clone())
+            .with(system::CreateSocketSystem, "create_port", &[])
+            .with(
+                system::DeleteSocketSystem,
+                "delete_socket",
+                &["create_port"],
+            )
+            .with(system::WelcomeSystem, "welcome", &["create_port"])
+            .with(system::WorldCommandSystem, "world_command", &[])
+            .with(system::ControlSystem, "control", &[])
+            .with(system::AgentCommandSystem, "agent_command", &[])
+            .with(system::RecordingCommandSystem, "recording_command", &[])
+            .with(system::ReportSystem, "report", &[])
+            .with(system::OutboxSystem, "outbox", &["create_port"])
+            .with(
+                system::CreateCommandSystem,
+                "create_command",
+                &["outbox", "agent_command"],
+            )
+            .build();
+        paused_dispatcher.setup(&mut world);
+
+        let mut state = State {
             world,
             dispatcher,
+            paused_dispatcher,
+            timings,
             frame: None,
+            frame_duration: options.frame_duration,
+            realtime: options.realtime,
+        };
+        state.load_scenario(scenario);
+        state
+    }
+
+    /// Replaces the world with the initial state described by the scenario.
+    /// The sheep in the scenario are created during the next frame.
+    pub fn load_scenario(&mut self, scenario: &Scenario) {
+        save::delete_marked(&mut self.world);
+
+        let map = scenario.world_map();
+        let (width, height) = map.grid_size();
+        self.world
+            .insert(snapshot::AllSheepSnapshot::new(width, height));
+        self.world
+            .insert(snapshot::RunningSheepSnapshot::new(width, height));
+        self.world.insert(scenario.params);
+
+        let mut create_cmds = CreateSheepCommandQueue::new();
+        {
+            let mut rng = self.world.fetch_mut::<SimRng>();
+            for group in &scenario.spawns {
+                for cmd in group.create_commands(&map, &mut rng) {
+                    create_cmds.push(cmd);
+                }
+            }
+        }
+        self.world.insert(create_cmds);
+        self.world.insert(map);
+        self.world.insert(ScenarioName(scenario.name.clone()));
+    }
+
+    /// Counts the entities of each kind, and the sheep with each behavior.
+    pub fn count_entities(&self) -> BTreeMap<(&'static str, Option<&'static str>), usize> {
+        let mut counts = BTreeMap::new();
+        for behavior in self
+            .world
+            .read_storage::<component::SheepBehaviorState>()
+            .join()
+        {
+            *counts
+                .entry(("sheep", Some(behavior.behavior.name())))
+                .or_insert(0) += 1;
+        }
+        let socket_count = self
+            .world
+            .read_storage::<component::Socket>()
+            .join()
+            .count();
+        counts.insert(("socket", None), socket_count);
+        counts
+    }
+
+    /// Makes the frame the current frame. Returns the number of frames since
+    /// the previous frame, which is 0 for the first frame.
+    pub fn start_frame(&mut self, frame: Frame) -> u64 {
+        let delta = self.frame.map_or(0, |prev| frame.number - prev.number);
+        self.frame = Some(frame);
+        self.world.insert(frame);
+        self.world.insert(DeltaFrame::new(delta));
+        delta
+    }
+
+    /// Executes the current frame of the simulation. While the simulation is
+    /// paused only messages and commands are handled.
+    pub fn run_frame(&mut self) {
+        if self.world.fetch_mut::<RunState>().advance() {
+            self.dispatcher.dispatch(&self.world);
+        } else {
+            self.paused_dispatcher.dispatch(&self.world);
+        }
+        self.world.maintain();
+        self.execute_world_commands();
+        self.answer_requests();
+    }
+
+    /// Creates the recording, analytics and trajectory files named in the
+    /// options.
+    pub fn open_outputs(&mut self, options: &Options) -> Result<(), String> {
+        if let Some(path) = &options.record_file {
+            let world = RecordedWorld::new(
+                &self.world.fetch::<ScenarioName>().0,
+                &self.world.fetch::<WorldMap>(),
+            );
+            let writer =
+                RecordingWriter::create(path, self.frame_duration.as_millis() as u64, world)
+                    .map_err(|err| format!("Unable to record to {}: {}", path.display(), err))?;
+            self.world.fetch_mut::<Recorder>().writer = Some(writer);
+            info!("Recording to {}", path.display());
+        }
+        if let Some(path) = &options.analytics_file {
+            let writer = AnalyticsWriter::create(path).map_err(|err| {
+                format!("Unable to write analytics to {}: {}", path.display(), err)
+            })?;
+            self.world.fetch_mut::<Analytics>().writer = Some(writer);
+            info!("Writing analytics to {}", path.display());
+        }
+        if let Some(path) = &options.trajectory_file {
+            let writer =
+                TrajectoryWriter::create(path, options.trajectory_interval).map_err(|err| {
+                    format!(
+                        "Unable to write trajectories to {}: {}",
+                        path.display(),
+                        err
+                    )
+                })?;
+            self.world.fetch_mut::<Trajectories>().writer = Some(writer);
+            info!("Writing trajectories to {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes the recording, analytics and trajectory files.
+    pub fn close_outputs(&mut self) {
+        if let Some(mut writer) = self.world.fetch_mut::<Recorder>().writer.take() {
+            if let Err(err) = writer.flush() {
+                error!("Unable to finish recording: {}", err);
+            }
+        }
+        if let Some(mut writer) = self.world.fetch_mut::<Analytics>().writer.take() {
+            if let Err(err) = writer.flush() {
+                error!("Unable to finish writing analytics: {}", err);
+            }
+        }
+        if let Some(mut writer) = self.world.fetch_mut::<Trajectories>().writer.take() {
+            if let Err(err) = writer.flush() {
+                error!("Unable to finish writing trajectories: {}", err);
+            }
+        }
+    }
+
+    /// Writes the world to the save file at the given path.
+    pub fn save(&self, path: &Path) -> SaveResult<()> {
+        let frame_number = self.frame.map_or(0, |f| f.number);
+        save::write(&self.world, frame_number, path)
+    }
+
+    /// Replaces the world with the contents of the save file at the given
+    /// path.
+    pub fn load(&mut self, path: &Path) -> SaveResult<()> {
+        let frame_number = save::read(&mut self.world, path)?;
+
+        // Sheep that were queued for creation belong to the replaced world.
+        self.world.fetch_mut::<CreateSheepCommandQueue>().clear();
+        self.frame = Some(Frame::starting_at(frame_number, self.frame_duration));
+        Ok(())
+    }
+
+    /// Replies to the queries in the inbox with the state of the world at the
+    /// end of the frame, and tells the senders of HTTP requests whether their
+    /// commands were handled. Clients whose commands were rejected are sent
+    /// the reason.
+    pub fn answer_requests(&mut self) {
+        let mut rejections = self.world.fetch_mut::<Rejections>().take();
+        let mut requests = vec![];
+        {
+            let mut inbox = self.world.fetch_mut::<Vec<network::IncomingMessage>>();
+            let mut outbox = self.world.fetch_mut::<Vec<network::OutgoingMessage>>();
+            let mut others = vec![];
+            for (index, msg) in inbox.drain(..).enumerate() {
+                let result = rejections.remove(&index).map_or(Ok(()), Err);
+                match msg {
+                    network::IncomingMessage::Query { .. }
+                    | network::IncomingMessage::Request { .. } => requests.push((msg, result)),
+                    msg => {
+                        if let (Some(sender), Err(reason)) = (msg.sender(), result) {
+                            outbox.push(network::OutgoingMessage::error(sender, reason));
+                        }
+                        others.push(msg);
+                    }
+                }
+            }
+            *inbox = others;
+        }
+
+        for (msg, result) in requests {
+            match msg {
+                network::IncomingMessage::Query { query, reply, .. } => {
+                    let _ = reply.send(self.query_json(query));
+                }
+                network::IncomingMessage::Request { reply, .. } => {
+                    let _ = reply.send(result);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn query_json(&self, query: network::Query) -> String {
+        let params = self.world.fetch::<ModelParams>();
+        let json = match query {
+            network::Query::State => {
+                let scenario = self.world.fetch::<ScenarioName>();
+                let pos_storage = self.world.read_storage::<component::Position>();
+                let heading_storage = self.world.read_storage::<component::Heading>();
+                serde_json::to_string(&WorldSummary {
+                    scenario: &scenario.0,
+                    frame: self.frame.map_or(0, |f| f.number),
+                    params: &params,
+                    agent_states: (&pos_storage, &heading_storage)
+                        .join()
+                        .map(|(pos, heading)| {
+                            network::AgentState::new(pos.v.x, pos.v.y, heading.r.angle())
+                        })
+                        .collect(),
+                })
+            }
+            network::Query::Params => serde_json::to_string(&*params),
+            network::Query::Profile => {
+                serde_json::to_string(&self.world.fetch::<Profile>().report())
+            }
+            network::Query::Analytics => {
+                serde_json::to_string(&self.world.fetch::<Analytics>().latest)
+            }
+        };
+        json.expect("World summaries are always serializable.")
+    }
+
+    /// Executes the world commands that were queued during the frame.
+    pub fn execute_world_commands(&mut self) {
+        let commands: Vec<(usize, WorldCommand)> = self
+            .world
+            .fetch_mut::<WorldCommandQueue>()
+            .commands
+            .drain(..)
+            .collect();
+
+        for (index, cmd) in commands {
+            let rejection = match cmd {
+                WorldCommand::Save(path) => match self.save(&path) {
+                    Ok(()) => {
+                        info!("Saved world to {}", path.display());
+                        None
+                    }
+                    Err(err) => {
+                        warn!("Unable to save world to {}: {}", path.display(), err);
+                        Some(format!("Unable to save world: {}", err))
+                    }
+                },
+                WorldCommand::Load(path) => match self.load(&path) {
+                    Ok(()) => {
+                        info!("Loaded world from {}", path.display());
+                        None
+                    }
+                    Err(err) => {
+                        warn!("Unable to load world from {}: {}", path.display(), err);
+                        Some(format!("Unable to load world: {}", err))
+                    }
+                },
+                WorldCommand::LoadScenario(path) => match scenario::read(&path) {
+                    Ok(scenario) => {
+                        self.load_scenario(&scenario);
+                        info!("Loaded scenario {}", scenario.name);
+                        None
+                    }
+                    Err(err) => {
+                        warn!("Unable to load scenario {}: {}", path.display(), err);
+                        Some(format!("Unable to load scenario: {}", err))
+                    }
+                },
+            };
+            if let Some(reason) = rejection {
+                self.world.fetch_mut::<Rejections>().reject(index, reason);
+            }
         }
     }
 
@@ -78,25 +453,14 @@ impl State<'_, '_> {
 
         let outbox: Vec<network::OutgoingMessage> = vec![];
         world.insert(outbox);
-    }
 
-    fn initialize_cmd_queue(world: &mut World) {
-        let mut create_cmds = CreateSheepCommandQueue::new();
-        for x in 1..=5 {
-            for y in 1..=5 {
-                create_cmds.push(CreateSheepCommand {
-                    position: component::Position::new((x * 3) as f32, (y * 3) as f32),
-                    heading: component::Heading::new(0.0),
-                    velocity: component::Velocity::new(0.0, 0.0),
-                    behavior: component::SheepBehaviorState::new(component::SheepBehavior::Walking),
-                })
-            }
-        }
-        world.insert(create_cmds);
+        let broadcasts: Vec<network::Broadcast> = vec![];
+        world.insert(broadcasts);
     }
 
-    fn initialize_snapshots(world: &mut World) {
-        world.insert(snapshot::AllSheepSnapshot::new(16, 16));
-        world.insert(snapshot::RunningSheepSnapshot::new(16, 16));
+    fn initialize_cmd_queues(world: &mut World) {
+        world.insert(CreateSheepCommandQueue::new());
+        world.insert(WorldCommandQueue::new());
+        world.insert(Rejections::default());
     }
 }
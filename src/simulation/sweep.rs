@@ -0,0 +1,385 @@
+use super::{
+    analytics::Analytics,
+    frame::Frame,
+    network,
+    options::Options,
+    params::ModelParams,
+    scenario::{self, Scenario},
+    state::State,
+};
+use crate::network::FlockStats;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+    thread,
+    time::Instant,
+};
+use tracing::{error, info};
+
+/// Definition of a parameter sweep. Sweeps are read from TOML files.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sweep {
+    /// Scenario file from which every run is initialized. The default scenario
+    /// is used if no file is given.
+    #[serde(default)]
+    pub scenario: Option<PathBuf>,
+
+    /// Number of frames simulated in each run.
+    pub frames: u64,
+
+    /// Number of frames at the start of each run that are left out of the
+    /// averages in the summary.
+    #[serde(default)]
+    pub burn_in: u64,
+
+    /// Seeds with which every combination of parameters is run.
+    #[serde(default = "default_seeds")]
+    pub seeds: Vec<u64>,
+
+    /// Values of each swept model parameter. Parameters that aren't swept keep
+    /// the scenario's values.
+    #[serde(default)]
+    pub params: BTreeMap<String, Vec<Value>>,
+
+    /// If true then the analytics and trajectories of each run are written to
+    /// a directory of their own.
+    #[serde(default)]
+    pub full_outputs: bool,
+}
+
+fn default_seeds() -> Vec<u64> {
+    vec![0]
+}
+
+/// A single simulation of a sweep.
+#[derive(Clone, Debug)]
+pub struct Run {
+    pub number: usize,
+    pub seed: u64,
+
+    /// Values of the swept parameters.
+    pub changes: Map<String, Value>,
+
+    pub params: ModelParams,
+}
+
+/// Measurements of the flock during a run.
+#[derive(Debug)]
+pub struct Summary {
+    /// Polarization and milling averaged over the frames after the burn-in.
+    pub mean_polarization: f32,
+    pub mean_milling: f32,
+
+    /// Statistics of the last frame.
+    pub last: FlockStats,
+
+    /// Wall-clock time taken by the run in seconds.
+    pub seconds: f64,
+}
+
+impl Sweep {
+    pub fn read(path: &Path) -> Result<Sweep, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let sweep: Sweep = toml::from_str(&text).map_err(|err| err.to_string())?;
+        if sweep.frames <= sweep.burn_in {
+            return Err(format!(
+                "The number of frames ({}) must exceed the burn-in ({})",
+                sweep.frames, sweep.burn_in
+            ));
+        }
+        if sweep.seeds.is_empty() {
+            return Err("At least one seed is required".to_string());
+        }
+        if let Some((name, _)) = sweep.params.iter().find(|(_, values)| values.is_empty()) {
+            return Err(format!("No values are given for {}", name));
+        }
+        Ok(sweep)
+    }
+
+    /// Gets a run for every combination of parameter values and seeds. Fails
+    /// if any combination isn't a valid set of parameters, so that mistakes
+    /// are found before anything is simulated.
+    pub fn runs(&self, base: &ModelParams) -> Result<Vec<Run>, String> {
+        let mut combinations = vec![Map::new()];
+        for (name, values) in &self.params {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|changes| {
+                    values.iter().map(move |value| {
+                        let mut changes = changes.clone();
+                        changes.insert(name.clone(), value.clone());
+                        changes
+                    })
+                })
+                .collect();
+        }
+
+        let mut runs = vec![];
+        for changes in combinations {
+            let params = base.with_changes(&changes).map_err(|err| {
+                format!(
+                    "Invalid parameters {}: {}",
+                    Value::from(changes.clone()),
+                    err
+                )
+            })?;
+            for &seed in &self.seeds {
+                runs.push(Run {
+                    number: runs.len(),
+                    seed,
+                    changes: changes.clone(),
+                    params,
+                });
+            }
+        }
+        Ok(runs)
+    }
+}
+
+/// Runs every simulation of the sweep defined in the given file, spreading
+/// the runs across `jobs` threads. A summary row is written to `summary.csv`
+/// in the output directory as each run finishes. The frame duration, cluster
+/// distance and trajectory interval are taken from the options.
+pub fn sweep(path: &Path, output_dir: &Path, jobs: usize, options: &Options) -> Result<(), String> {
+    let sweep = Sweep::read(path)
+        .map_err(|err| format!("Unable to read sweep {}: {}", path.display(), err))?;
+    let scenario = match &sweep.scenario {
+        Some(path) => scenario::read(path)
+            .map_err(|err| format!("Unable to load scenario {}: {}", path.display(), err))?,
+        None => Scenario::default(),
+    };
+    let runs = sweep.runs(&scenario.params)?;
+
+    let summary_path = output_dir.join("summary.csv");
+    let names: Vec<&str> = sweep.params.keys().map(String::as_str).collect();
+    let writer = SummaryWriter::create(&summary_path, &names)
+        .map_err(|err| format!("Unable to write {}: {}", summary_path.display(), err))?;
+    let writer = Mutex::new(writer);
+    let jobs = jobs.clamp(1, runs.len());
+    info!(
+        "Running {} simulations of scenario {} on {} threads",
+        runs.len(),
+        scenario.name,
+        jobs
+    );
+
+    // Each thread takes the next run that hasn't been started until none are
+    // left. A run that panics counts as a failure rather than stopping the
+    // sweep.
+    let next_run = AtomicUsize::new(0);
+    let failures = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                while let Some(run) = runs.get(next_run.fetch_add(1, Ordering::Relaxed)) {
+                    let run_dir = sweep
+                        .full_outputs
+                        .then(|| output_dir.join(format!("run-{}", run.number)));
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        execute(&sweep, &scenario, run, options, run_dir)
+                    }))
+                    .unwrap_or_else(|_| Err("The simulation panicked".to_string()))
+                    .and_then(|summary| {
+                        writer
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .write(run, &summary)
+                            .map_err(|err| format!("Unable to write summary: {}", err))
+                    });
+                    match result {
+                        Ok(()) => info!("Finished run {}", run.number),
+                        Err(err) => {
+                            error!("Run {} failed: {}", run.number, err);
+                            failures.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    match failures.into_inner() {
+        0 => {
+            info!("Wrote summary to {}", summary_path.display());
+            Ok(())
+        }
+        failed => Err(format!("{} of {} runs failed", failed, runs.len())),
+    }
+}
+
+/// Simulates a run as quickly as possible, writing its analytics and
+/// trajectories to the given directory if there is one.
+fn execute(
+    sweep: &Sweep,
+    scenario: &Scenario,
+    run: &Run,
+    options: &Options,
+    dir: Option<PathBuf>,
+) -> Result<Summary, String> {
+    let start = Instant::now();
+    let scenario = Scenario {
+        params: run.params,
+        ..scenario.clone()
+    };
+    let options = Options {
+        analytics_file: dir.as_ref().map(|dir| dir.join("analytics.csv")),
+        trajectory_file: dir.as_ref().map(|dir| dir.join("trajectories.csv")),
+        seed: Some(run.seed),
+        realtime: false,
+        max_frames: Some(sweep.frames),
+        ..options.clone()
+    };
+    let mut state = State::new(&scenario, &options);
+    state.open_outputs(&options)?;
+
+    let mut polarization = 0.0;
+    let mut milling = 0.0;
+    let mut frame = Frame::new(options.frame_duration);
+    for number in 0..sweep.frames {
+        if number > 0 {
+            frame = frame.advance();
+        }
+        state.start_frame(frame);
+        state.run_frame();
+
+        // Nobody is listening, so messages and timings are discarded.
+        state
+            .world
+            .fetch_mut::<Vec<network::OutgoingMessage>>()
+            .clear();
+        state.world.fetch_mut::<Vec<network::Broadcast>>().clear();
+        state.timings.take();
+
+        if number >= sweep.burn_in {
+            let analytics = state.world.fetch::<Analytics>();
+            polarization += analytics.latest.polarization;
+            milling += analytics.latest.milling;
+        }
+    }
+    state.close_outputs();
+
+    let averaged_frames = (sweep.frames - sweep.burn_in) as f32;
+    let last = state.world.fetch::<Analytics>().latest.clone();
+    Ok(Summary {
+        mean_polarization: polarization / averaged_frames,
+        mean_milling: milling / averaged_frames,
+        last,
+        seconds: start.elapsed().as_secs_f64(),
+    })
+}
+
+/// Writes a row to a CSV file for each run of a sweep.
+struct SummaryWriter {
+    writer: BufWriter<File>,
+    names: Vec<String>,
+}
+
+impl SummaryWriter {
+    /// Creates a CSV file at the given path and writes its header, which has a
+    /// column for each of the named parameters.
+    fn create(path: &Path, names: &[&str]) -> io::Result<SummaryWriter> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut header = vec!["run", "seed"];
+        header.extend(names);
+        header.extend(&[
+            "agents",
+            "mean_polarization",
+            "mean_milling",
+            "polarization",
+            "milling",
+            "mean_nearest_neighbor_distance",
+            "clusters",
+            "largest_cluster",
+            "stationary",
+            "walking",
+            "running",
+            "seconds",
+        ]);
+        writeln!(writer, "{}", header.join(","))?;
+        writer.flush()?;
+        Ok(SummaryWriter {
+            writer,
+            names: names.iter().map(|name| name.to_string()).collect(),
+        })
+    }
+
+    /// Writes the summary of a run. Rows are flushed right away so that the
+    /// runs that finished are kept if the sweep is interrupted.
+    fn write(&mut self, run: &Run, summary: &Summary) -> io::Result<()> {
+        let stats = &summary.last;
+        let fraction = |name: &str| stats.behavior_fractions.get(name).copied().unwrap_or(0.0);
+        let mut row = vec![run.number.to_string(), run.seed.to_string()];
+        row.extend(self.names.iter().map(|name| {
+            run.changes
+                .get(name)
+                .map_or_else(String::new, Value::to_string)
+        }));
+        row.extend(vec![
+            stats.agents.to_string(),
+            summary.mean_polarization.to_string(),
+            summary.mean_milling.to_string(),
+            stats.polarization.to_string(),
+            stats.milling.to_string(),
+            stats
+                .mean_nearest_neighbor_distance
+                .map_or_else(String::new, |d| d.to_string()),
+            stats.clusters.to_string(),
+            stats
+                .cluster_sizes
+                .first()
+                .copied()
+                .unwrap_or(0)
+                .to_string(),
+            fraction("stationary").to_string(),
+            fraction("walking").to_string(),
+            fraction("running").to_string(),
+            summary.seconds.to_string(),
+        ]);
+        writeln!(self.writer, "{}", row.join(","))?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sweep;
+    use crate::simulation::params::ModelParams;
+
+    #[test]
+    fn every_combination_is_run_with_every_seed() {
+        let sweep: Sweep = toml::from_str(
+            r#"
+            frames = 10
+            seeds = [1, 2]
+            [params]
+            noise = [0.1, 0.2, 0.3]
+            walking_speed = [0.15, 0.3]
+            "#,
+        )
+        .unwrap();
+        let runs = sweep.runs(&ModelParams::default()).unwrap();
+        assert_eq!(runs.len(), 12);
+        assert_eq!(runs[0].seed, 1);
+        assert_eq!(runs[1].seed, 2);
+        assert_eq!(runs[3].params.walking_speed, 0.3);
+        assert_eq!(runs[11].params.noise, 0.3);
+        assert_eq!(runs[11].params.running_speed, 1.5);
+        assert!(runs.iter().enumerate().all(|(i, run)| run.number == i));
+
+        let typo: Sweep = toml::from_str("frames = 10\n[params]\nnoyse = [0.1]").unwrap();
+        assert!(typo.runs(&ModelParams::default()).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::network::{ClientCommand, IncomingMessage};
+use crate::simulation::command_queue::CreateSheepCommandQueue;
+use crate::simulation::component::Position;
+use crate::simulation::rejections::Rejections;
+use crate::simulation::rng::SimRng;
+use crate::simulation::world_map::WorldMap;
+use specs::prelude::*;
+use tracing::{info, warn};
+
+pub struct AgentCommandSystem;
+
+impl<'a> System<'a> for AgentCommandSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<IncomingMessage>>,
+        ReadExpect<'a, WorldMap>,
+        WriteExpect<'a, SimRng>,
+        WriteExpect<'a, CreateSheepCommandQueue>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, Rejections>,
+    );
+
+    /// Queues the creation of the agents that clients spawn and deletes the
+    /// agents that clients delete.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, map, mut rng, mut create_queue, entities, pos_storage, mut rejections) = data;
+
+        for (index, msg) in inbox.iter().enumerate() {
+            match msg.command() {
+                Some(ClientCommand::Spawn(group)) => {
+                    if let Err(err) = group.validate() {
+                        warn!("Unable to spawn agents: {}", err);
+                        rejections.reject(index, format!("Unable to spawn agents: {}", err));
+                        continue;
+                    }
+                    let cmds = group.create_commands(&map, &mut rng);
+                    info!("Spawning {} agents", cmds.len());
+                    for cmd in cmds {
+                        create_queue.push(cmd);
+                    }
+                }
+                Some(ClientCommand::DeleteAgents { area }) => {
+                    let mut count = 0;
+                    for (e, pos) in (&entities, &pos_storage).join() {
+                        if area.is_none_or(|area| area.contains(pos.v.x, pos.v.y)) {
+                            let _ = entities.delete(e);
+                            count += 1;
+                        }
+                    }
+                    info!("Deleted {} agents", count);
+                }
+                _ => {}
+            }
+        }
+    }
+}
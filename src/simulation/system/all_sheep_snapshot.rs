@@ -1,6 +1,7 @@
 use crate::simulation::component::{Heading, Position, SheepBehaviorState};
 use crate::simulation::grid::Grid;
 use crate::simulation::snapshot::{AllSheepSnapshot, AllSheepSnapshotCell};
+use crate::simulation::world_map::WorldMap;
 use nalgebra::Vector2;
 use specs::prelude::*;
 
@@ -10,16 +11,20 @@ impl<'a> System<'a> for AllSheepSnapshotSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         WriteExpect<'a, AllSheepSnapshot>,
+        ReadExpect<'a, WorldMap>,
         ReadStorage<'a, SheepBehaviorState>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Heading>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut snapshot, behavior_storate, pos_storage, heading_storage) = data;
+        let (mut snapshot, map, behavior_storate, pos_storage, heading_storage) = data;
 
         for (_, pos, heading) in (&behavior_storate, &pos_storage, &heading_storage).join() {
-            let grid_pos = (pos.v.x as usize % 5, pos.v.y as usize % 5);
+            let grid_pos = match map.cell_at(pos.v) {
+                Some(grid_pos) => grid_pos,
+                None => continue,
+            };
             let new_cell = snapshot.grid.at(grid_pos).map(|c| {
                 let heading_vec = heading.r * Vector2::x();
                 AllSheepSnapshotCell {
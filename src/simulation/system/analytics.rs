@@ -0,0 +1,49 @@
+use crate::simulation::analytics::{self, Analytics, Sample};
+use crate::simulation::component::{Heading, Position, SheepBehaviorState};
+use crate::simulation::frame::Frame;
+use crate::simulation::world_map::WorldMap;
+use nalgebra::Vector2;
+use specs::prelude::*;
+use tracing::warn;
+
+pub struct AnalyticsSystem;
+
+impl<'a> System<'a> for AnalyticsSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Frame>,
+        ReadExpect<'a, WorldMap>,
+        WriteExpect<'a, Analytics>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Heading>,
+        ReadStorage<'a, SheepBehaviorState>,
+    );
+
+    /// Computes the statistics of the flock and writes them to the analytics
+    /// file if there is one.
+    fn run(&mut self, data: Self::SystemData) {
+        let (frame, map, mut analytics, pos_storage, heading_storage, behavior_storage) = data;
+
+        let samples: Vec<Sample> = (&pos_storage, &heading_storage, &behavior_storage)
+            .join()
+            .map(|(pos, heading, behavior)| Sample {
+                position: pos.v,
+                heading: heading.r * Vector2::x(),
+                behavior: behavior.behavior,
+            })
+            .collect();
+        let cluster_distance = analytics.cluster_distance.unwrap_or(map.cell_size);
+        analytics.latest = analytics::flock_stats(frame.number, &samples, cluster_distance);
+
+        let Analytics { latest, writer, .. } = &mut *analytics;
+        if let Some(w) = writer {
+            if let Err(err) = w.write(latest) {
+                warn!(
+                    "Unable to write analytics for frame {}: {}",
+                    frame.number, err
+                );
+                *writer = None;
+            }
+        }
+    }
+}
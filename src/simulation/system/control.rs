@@ -0,0 +1,57 @@
+use crate::network::{ClientCommand, IncomingMessage};
+use crate::simulation::params::ModelParams;
+use crate::simulation::rejections::Rejections;
+use crate::simulation::run_state::RunState;
+use specs::prelude::*;
+use tracing::{info, warn};
+
+pub struct ControlSystem;
+
+impl<'a> System<'a> for ControlSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<IncomingMessage>>,
+        WriteExpect<'a, RunState>,
+        WriteExpect<'a, ModelParams>,
+        WriteExpect<'a, Rejections>,
+    );
+
+    /// Pauses, resumes, or steps the simulation and changes the model
+    /// parameters when requested. Playback commands are rejected, since only
+    /// replays can honor them.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, mut run_state, mut params, mut rejections) = data;
+
+        for (index, msg) in inbox.iter().enumerate() {
+            match msg.command() {
+                Some(ClientCommand::Pause) => {
+                    run_state.paused = true;
+                    run_state.steps = 0;
+                    info!("Paused");
+                }
+                Some(ClientCommand::Resume) => {
+                    run_state.paused = false;
+                    info!("Resumed");
+                }
+                Some(ClientCommand::Step { frames }) => {
+                    run_state.paused = true;
+                    run_state.steps = *frames;
+                }
+                Some(ClientCommand::SetParams(changes)) => match params.with_changes(changes) {
+                    Ok(changed) => {
+                        *params = changed;
+                        info!("Changed model parameters to {:?}", *params);
+                    }
+                    Err(err) => {
+                        warn!("Unable to change model parameters: {}", err);
+                        rejections.reject(index, format!("Invalid model parameters: {}", err));
+                    }
+                },
+                Some(ClientCommand::Seek { .. }) | Some(ClientCommand::SetSpeed { .. }) => {
+                    rejections.reject(index, "Only replays can seek or change speed".to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::simulation::command_queue::CreateSheepCommandQueue;
 use crate::simulation::component::{Heading, Position, SheepBehaviorState, Velocity};
-use specs::prelude::*;
+use crate::simulation::save::{AgentMarker, AgentMarkerAllocator};
+use specs::{prelude::*, saveload::MarkerAllocator};
 
 pub struct CreateCommandSystem;
 
@@ -13,6 +14,8 @@ impl<'a> System<'a> for CreateCommandSystem {
         WriteStorage<'a, Heading>,
         WriteStorage<'a, Velocity>,
         WriteStorage<'a, SheepBehaviorState>,
+        WriteStorage<'a, AgentMarker>,
+        WriteExpect<'a, AgentMarkerAllocator>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -23,6 +26,8 @@ impl<'a> System<'a> for CreateCommandSystem {
             mut heading_storage,
             mut vel_storage,
             mut behavior_storage,
+            mut marker_storage,
+            mut marker_allocator,
         ) = data;
 
         for cmd in command_queue.commands.iter() {
@@ -39,6 +44,7 @@ impl<'a> System<'a> for CreateCommandSystem {
             behavior_storage
                 .insert(e, cmd.behavior)
                 .expect("Unable to insert behavior.");
+            marker_allocator.mark(e, &mut marker_storage);
         }
 
         command_queue.clear();
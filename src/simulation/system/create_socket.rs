@@ -12,20 +12,20 @@ impl<'a> System<'a> for CreateSocketSystem {
         WriteStorage<'a, Socket>,
     );
 
-    /// Creates a socket for each sender in the inbox if the socket does not
+    /// Creates a socket for each client that connected if the socket does not
     /// exist yet.
     fn run(&mut self, data: Self::SystemData) {
         let (inbox, entities, mut socket_storage) = data;
 
         for msg in &*inbox {
-            if socket_storage
-                .join()
-                .find(|&&s| s.addr == msg.sender)
-                .is_none()
-            {
+            let sender = match msg {
+                network::IncomingMessage::Connected { sender, .. } => *sender,
+                _ => continue,
+            };
+            if socket_storage.join().find(|&&s| s.addr == sender).is_none() {
                 let e = entities.create();
                 socket_storage
-                    .insert(e, Socket::new(msg.sender))
+                    .insert(e, Socket::new(sender))
                     .expect("Unable to insert position.");
             }
         }
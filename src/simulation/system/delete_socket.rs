@@ -0,0 +1,30 @@
+use crate::simulation::component::Socket;
+use crate::simulation::network;
+use specs::prelude::*;
+
+pub struct DeleteSocketSystem;
+
+impl<'a> System<'a> for DeleteSocketSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<network::IncomingMessage>>,
+        Entities<'a>,
+        ReadStorage<'a, Socket>,
+    );
+
+    /// Deletes the socket entity of each client that disconnected, along with
+    /// any other components attached to it.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, entities, socket_storage) = data;
+
+        for msg in &*inbox {
+            if let network::IncomingMessage::Disconnected(addr) = msg {
+                for (e, socket) in (&entities, &socket_storage).join() {
+                    if socket.addr == *addr {
+                        entities.delete(e).expect("Unable to delete socket.");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::simulation::component::Position;
+use crate::simulation::frame::DeltaFrame;
+use specs::prelude::*;
+use tracing::{debug, trace};
+
+/// Number of sheep whose positions are logged each frame.
+const SAMPLE_SIZE: usize = 4;
+
+/// Logs the frame delta, the number of entities with positions, and the
+/// positions of a few of them. Only runs if diagnostics are enabled.
+pub struct DiagnosticsSystem;
+
+impl<'a> System<'a> for DiagnosticsSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, DeltaFrame>, ReadStorage<'a, Position>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (delta_resource, position_storage) = data;
+
+        let mut count = 0;
+        for pos in (&position_storage).join() {
+            if count < SAMPLE_SIZE {
+                trace!(x = pos.v.x, y = pos.v.y, "Sampled position");
+            }
+            count += 1;
+        }
+        debug!(
+            delta = delta_resource.delta,
+            entities = count,
+            "Diagnostics"
+        );
+    }
+}
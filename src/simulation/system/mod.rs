@@ -1,19 +1,39 @@
+mod agent_command;
 mod all_sheep_snapshot;
+mod analytics;
+mod control;
 mod create_command;
 mod create_socket;
-mod debug_log;
+mod delete_socket;
+mod diagnostics;
 mod outbox;
 mod position;
+mod record;
+mod recording_command;
+mod report;
 mod reset_all_sheep_snapshot;
 mod sheep_heading;
 mod sheep_velocity;
+mod trajectory;
+mod welcome;
+mod world_command;
 
+pub use agent_command::AgentCommandSystem;
 pub use all_sheep_snapshot::AllSheepSnapshotSystem;
+pub use analytics::AnalyticsSystem;
+pub use control::ControlSystem;
 pub use create_command::CreateCommandSystem;
 pub use create_socket::CreateSocketSystem;
-pub use debug_log::DebugLogSystem;
+pub use delete_socket::DeleteSocketSystem;
+pub use diagnostics::DiagnosticsSystem;
 pub use outbox::OutboxSystem;
 pub use position::PositionSystem;
+pub use record::RecordSystem;
+pub use recording_command::RecordingCommandSystem;
+pub use report::ReportSystem;
 pub use reset_all_sheep_snapshot::ResetAllSheepSnapshotSystem;
 pub use sheep_heading::SheepHeadingSystem;
 pub use sheep_velocity::SheepVelocitySystem;
+pub use trajectory::TrajectorySystem;
+pub use welcome::WelcomeSystem;
+pub use world_command::WorldCommandSystem;
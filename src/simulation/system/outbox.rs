@@ -1,32 +1,66 @@
 use crate::network;
+use crate::simulation::broadcast::BroadcastSchedule;
 use crate::simulation::component::{Heading, Position, Socket};
+use crate::simulation::frame::Frame;
 use specs::prelude::*;
+use std::net::SocketAddr;
 
 pub struct OutboxSystem;
 
 impl<'a> System<'a> for OutboxSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        WriteExpect<'a, Vec<network::OutgoingMessage>>,
-        Entities<'a>,
+        ReadExpect<'a, Vec<network::IncomingMessage>>,
+        ReadExpect<'a, Frame>,
+        WriteExpect<'a, BroadcastSchedule>,
+        WriteExpect<'a, Vec<network::Broadcast>>,
         ReadStorage<'a, Socket>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Heading>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut outbox, entities, socket_storage, pos_storage, heading_storage) = data;
+        let (
+            inbox,
+            frame,
+            mut schedule,
+            mut broadcasts,
+            socket_storage,
+            pos_storage,
+            heading_storage,
+        ) = data;
 
-        // FIXME: This will be inefficent with >1 client since we'll loop
-        // through all entities for each client. See below for better solution.
-        for socket in socket_storage.join() {
-            let mut msg = network::OutgoingMessage::new(socket.addr);
-            for (pos, heading) in (&pos_storage, &heading_storage).join() {
-                msg.with_agent_state(pos.v.x, pos.v.y, heading.r.angle());
-            }
-            outbox.push(msg);
+        // Clients that connected during the frame are sent the world state
+        // right away instead of waiting for the next broadcast.
+        let connected: Vec<SocketAddr> = inbox
+            .iter()
+            .filter_map(|msg| match msg {
+                network::IncomingMessage::Connected { sender, .. } => Some(*sender),
+                _ => None,
+            })
+            .collect();
+        let is_broadcast = schedule.try_broadcast(frame.number);
+
+        let recipients: Vec<SocketAddr> = socket_storage
+            .join()
+            .map(|socket| socket.addr)
+            .filter(|addr| is_broadcast || connected.contains(addr))
+            .collect();
+        if recipients.is_empty() {
+            return;
         }
 
+        // The world state is built and serialized once no matter how many
+        // clients it is sent to.
+        let agent_states = (&pos_storage, &heading_storage)
+            .join()
+            .map(|(pos, heading)| network::AgentState::new(pos.v.x, pos.v.y, heading.r.angle()))
+            .collect();
+        broadcasts.push(network::Broadcast {
+            recipients,
+            message: network::SharedMessage::world_state(agent_states),
+        });
+
         // let query = (
         //     (&pos_storage).maybe(),
         //     (&heading_storage).maybe(),
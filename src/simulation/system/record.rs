@@ -0,0 +1,37 @@
+use crate::network::AgentState;
+use crate::simulation::component::{Heading, Position};
+use crate::simulation::frame::Frame;
+use crate::simulation::recording::{RecordedFrame, Recorder};
+use specs::prelude::*;
+use tracing::warn;
+
+pub struct RecordSystem;
+
+impl<'a> System<'a> for RecordSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Frame>,
+        WriteExpect<'a, Recorder>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Heading>,
+    );
+
+    /// Writes the state of every agent to the recording in progress.
+    fn run(&mut self, data: Self::SystemData) {
+        let (frame, mut recorder, pos_storage, heading_storage) = data;
+
+        if let Some(writer) = &mut recorder.writer {
+            let recorded_frame = RecordedFrame {
+                frame: frame.number,
+                agent_states: (&pos_storage, &heading_storage)
+                    .join()
+                    .map(|(pos, heading)| AgentState::new(pos.v.x, pos.v.y, heading.r.angle()))
+                    .collect(),
+            };
+            if let Err(err) = writer.write_frame(&recorded_frame) {
+                warn!("Unable to write frame {}: {}", frame.number, err);
+                recorder.writer = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::network::{ClientCommand, IncomingMessage};
+use crate::simulation::frame::Frame;
+use crate::simulation::path::Directories;
+use crate::simulation::recording::{self, RecordedWorld, Recorder, RecordingWriter};
+use crate::simulation::rejections::Rejections;
+use crate::simulation::scenario::ScenarioName;
+use crate::simulation::world_map::WorldMap;
+use specs::prelude::*;
+use tracing::{info, warn};
+
+pub struct RecordingCommandSystem;
+
+impl<'a> System<'a> for RecordingCommandSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<IncomingMessage>>,
+        ReadExpect<'a, Frame>,
+        ReadExpect<'a, Directories>,
+        ReadExpect<'a, ScenarioName>,
+        ReadExpect<'a, WorldMap>,
+        WriteExpect<'a, Recorder>,
+        WriteExpect<'a, Rejections>,
+    );
+
+    /// Starts or stops recording when requested by a client. Recording can be
+    /// started and stopped while the simulation is paused.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, frame, dirs, scenario_name, map, mut recorder, mut rejections) = data;
+
+        for (index, msg) in inbox.iter().enumerate() {
+            match msg.command() {
+                Some(ClientCommand::StartRecording { name }) => {
+                    let frame_millis = frame.duration.as_millis() as u64;
+                    let world = RecordedWorld::new(&scenario_name.0, &map);
+                    let writer = recording::path_in(&dirs.recordings, name)
+                        .and_then(|path| RecordingWriter::create(&path, frame_millis, world));
+                    match writer {
+                        Ok(writer) => {
+                            info!("Started recording {}", name);
+                            stop(&mut recorder);
+                            recorder.writer = Some(writer);
+                        }
+                        Err(err) => {
+                            warn!("Unable to start recording {}: {}", name, err);
+                            rejections.reject(index, format!("Unable to start recording: {}", err));
+                        }
+                    }
+                }
+                Some(ClientCommand::StopRecording) => stop(&mut recorder),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Flushes and closes the recording in progress.
+fn stop(recorder: &mut Recorder) {
+    if let Some(mut writer) = recorder.writer.take() {
+        match writer.flush() {
+            Ok(()) => info!("Stopped recording"),
+            Err(err) => warn!("Unable to finish recording: {}", err),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::network::{ClientCommand, IncomingMessage, OutgoingMessage};
+use crate::simulation::analytics::Analytics;
+use crate::simulation::profile::Profile;
+use specs::prelude::*;
+
+pub struct ReportSystem;
+
+impl<'a> System<'a> for ReportSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<IncomingMessage>>,
+        WriteExpect<'a, Vec<OutgoingMessage>>,
+        ReadExpect<'a, Profile>,
+        ReadExpect<'a, Analytics>,
+    );
+
+    /// Replies to clients that ask for the time taken by each system or for
+    /// the statistics of the flock.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, mut outbox, profile, analytics) = data;
+
+        for msg in &*inbox {
+            let sender = match msg.sender() {
+                Some(sender) => sender,
+                None => continue,
+            };
+            match msg.command() {
+                Some(ClientCommand::GetProfile) => {
+                    outbox.push(OutgoingMessage::profile(sender, profile.report()));
+                }
+                Some(ClientCommand::GetAnalytics) => {
+                    outbox.push(OutgoingMessage::analytics(sender, analytics.latest.clone()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -1,16 +1,19 @@
 use crate::simulation::grid::CellBlockBuilder;
 use crate::simulation::snapshot::{AllSheepSnapshot, AllSheepSnapshotCell};
+use crate::simulation::world_map::WorldMap;
 use specs::prelude::*;
 
 pub struct ResetAllSheepSnapshotSystem;
 
 impl<'a> System<'a> for ResetAllSheepSnapshotSystem {
     #[allow(clippy::type_complexity)]
-    type SystemData = WriteExpect<'a, AllSheepSnapshot>;
+    type SystemData = (WriteExpect<'a, AllSheepSnapshot>, ReadExpect<'a, WorldMap>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let mut snapshot = data;
-        // TODO: Implement and use a mutable CellBolock iterator. Don't take width and height params.
-        snapshot.grid = CellBlockBuilder::new(16, 16, AllSheepSnapshotCell::default()).finish()
+        let (mut snapshot, map) = data;
+        // TODO: Implement and use a mutable CellBolock iterator.
+        let (width, height) = map.grid_size();
+        snapshot.grid =
+            CellBlockBuilder::new(width, height, AllSheepSnapshotCell::default()).finish()
     }
 }
@@ -1,6 +1,9 @@
 use crate::simulation::component::{Heading, Position, SheepBehavior, SheepBehaviorState};
 use crate::simulation::grid::Grid;
+use crate::simulation::params::ModelParams;
+use crate::simulation::rng::SimRng;
 use crate::simulation::snapshot::{AllSheepSnapshot, AllSheepSnapshotCell};
+use crate::simulation::world_map::WorldMap;
 use nalgebra::{Rotation2, Vector2};
 use rand::distributions::{Distribution, Uniform};
 use specs::prelude::*;
@@ -11,21 +14,39 @@ impl<'a> System<'a> for SheepHeadingSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, AllSheepSnapshot>,
+        ReadExpect<'a, WorldMap>,
+        ReadExpect<'a, ModelParams>,
+        WriteExpect<'a, SimRng>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, SheepBehaviorState>,
         WriteStorage<'a, Heading>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (snapshot_rsrc, pos_storage, behavior_storage, mut heading_storage) = data;
+        let (
+            snapshot_rsrc,
+            map,
+            params,
+            mut rng,
+            pos_storage,
+            behavior_storage,
+            mut heading_storage,
+        ) = data;
 
-        for (pos, behavior, mut heading) in
+        for (pos, behavior, heading) in
             (&pos_storage, &behavior_storage, &mut heading_storage).join()
         {
             match behavior.behavior {
                 SheepBehavior::Stationary => {}
                 SheepBehavior::Walking => {
-                    heading.r = new_walking_heading(heading.r, pos.v, &snapshot_rsrc);
+                    heading.r = new_walking_heading(
+                        heading.r,
+                        pos.v,
+                        &snapshot_rsrc,
+                        &map,
+                        &params,
+                        &mut rng,
+                    );
                 }
                 SheepBehavior::Running => {}
             }
@@ -37,23 +58,29 @@ fn new_walking_heading(
     curr_heading: Rotation2<f32>,
     pos: Vector2<f32>,
     snapshot: &AllSheepSnapshot,
+    map: &WorldMap,
+    params: &ModelParams,
+    rng: &mut SimRng,
 ) -> Rotation2<f32> {
-    // TODO: Clean up.
-    let grid_pos = (pos.x as usize % 5, pos.y as usize % 5);
-    let cell = snapshot.grid.at(grid_pos);
+    let cell = map
+        .cell_at(pos)
+        .and_then(|grid_pos| snapshot.grid.at(grid_pos));
 
     // Get the mean heading from current cell.
     let next_without_noise = match cell {
         Some(AllSheepSnapshotCell {
             heading_sum: h_sum, ..
-        }) if h_sum.magnitude() > 0.1 => Rotation2::rotation_between(&Vector2::x(), h_sum),
+        }) if h_sum.magnitude() > params.alignment_threshold => {
+            Rotation2::rotation_between(&Vector2::x(), h_sum)
+        }
         _ => curr_heading,
     };
 
     // Add some noise to get the new heading.
-    let mut rng = rand::thread_rng();
-    const NOISE: f32 = 0.4082; // PI * 0.13
-    let noise_angle = Uniform::from(-1.0 * NOISE..NOISE).sample(&mut rng);
+    if params.noise <= 0.0 {
+        return next_without_noise;
+    }
+    let noise_angle = Uniform::from(-params.noise..params.noise).sample(&mut rng.rng);
     let noise_rot: Rotation2<f32> = Rotation2::new(noise_angle);
     next_without_noise * noise_rot
 }
@@ -1,4 +1,5 @@
 use crate::simulation::component::{Heading, SheepBehavior, SheepBehaviorState, Velocity};
+use crate::simulation::params::ModelParams;
 use nalgebra::Vector2;
 use specs::prelude::*;
 
@@ -7,21 +8,22 @@ pub struct SheepVelocitySystem;
 impl<'a> System<'a> for SheepVelocitySystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
+        ReadExpect<'a, ModelParams>,
         ReadStorage<'a, SheepBehaviorState>,
         ReadStorage<'a, Heading>,
         WriteStorage<'a, Velocity>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (behavior_storage, heading_storage, mut velocity_storage) = data;
+        let (params, behavior_storage, heading_storage, mut velocity_storage) = data;
 
-        for (behavior, heading, mut vel) in
+        for (behavior, heading, vel) in
             (&behavior_storage, &heading_storage, &mut velocity_storage).join()
         {
             vel.v = match behavior.behavior {
                 SheepBehavior::Stationary => nalgebra::zero(),
-                SheepBehavior::Walking => heading.r * (Vector2::x() * 0.15),
-                SheepBehavior::Running => heading.r * (Vector2::x() * 1.5),
+                SheepBehavior::Walking => heading.r * (Vector2::x() * params.walking_speed),
+                SheepBehavior::Running => heading.r * (Vector2::x() * params.running_speed),
             }
         }
     }
@@ -0,0 +1,65 @@
+use crate::simulation::component::{Heading, Position, SheepBehaviorState, Velocity};
+use crate::simulation::frame::Frame;
+use crate::simulation::save::AgentMarker;
+use crate::simulation::trajectory::{Trajectories, TrajectoryRow};
+use specs::{prelude::*, saveload::Marker};
+use tracing::warn;
+
+pub struct TrajectorySystem;
+
+impl<'a> System<'a> for TrajectorySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Frame>,
+        WriteExpect<'a, Trajectories>,
+        ReadStorage<'a, AgentMarker>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Heading>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, SheepBehaviorState>,
+    );
+
+    /// Writes the state of every agent to the trajectory file when a sample
+    /// is due.
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            frame,
+            mut trajectories,
+            marker_storage,
+            pos_storage,
+            heading_storage,
+            vel_storage,
+            behavior_storage,
+        ) = data;
+
+        let writer = match &mut trajectories.writer {
+            Some(writer) if writer.is_due(frame.number) => writer,
+            _ => return,
+        };
+        let rows = (
+            &marker_storage,
+            &pos_storage,
+            &heading_storage,
+            &vel_storage,
+            &behavior_storage,
+        )
+            .join()
+            .map(|(marker, pos, heading, vel, behavior)| TrajectoryRow {
+                frame: frame.number,
+                id: marker.id(),
+                x: pos.v.x,
+                y: pos.v.y,
+                heading: heading.r.angle(),
+                vx: vel.v.x,
+                vy: vel.v.y,
+                behavior: behavior.behavior.name(),
+            });
+        if let Err(err) = writer.write_frame(frame.number, rows) {
+            warn!(
+                "Unable to write trajectories for frame {}: {}",
+                frame.number, err
+            );
+            trajectories.writer = None;
+        }
+    }
+}
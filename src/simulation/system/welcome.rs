@@ -0,0 +1,48 @@
+use crate::network::{IncomingMessage, OutgoingMessage, Welcome, PROTOCOL_VERSION};
+use crate::simulation::frame::Frame;
+use crate::simulation::scenario::ScenarioName;
+use crate::simulation::world_map::WorldMap;
+use specs::prelude::*;
+
+pub struct WelcomeSystem;
+
+impl<'a> System<'a> for WelcomeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<IncomingMessage>>,
+        ReadExpect<'a, Frame>,
+        ReadExpect<'a, WorldMap>,
+        ReadExpect<'a, ScenarioName>,
+        WriteExpect<'a, Vec<OutgoingMessage>>,
+    );
+
+    /// Sends a welcome message describing the world to each client that
+    /// connected or joined the room.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, frame, map, scenario_name, mut outbox) = data;
+
+        for msg in &*inbox {
+            if let IncomingMessage::Connected {
+                sender,
+                client_id,
+                room,
+                role,
+            } = msg
+            {
+                let welcome = Welcome {
+                    protocol_version: PROTOCOL_VERSION,
+                    client_id: *client_id,
+                    room: room.clone(),
+                    role: *role,
+                    scenario: scenario_name.0.clone(),
+                    bounds: map.bounds,
+                    cell_size: map.cell_size,
+                    grid_size: map.grid_size(),
+                    frame_duration: frame.duration.as_secs_f64(),
+                    frame: frame.number,
+                };
+                outbox.push(OutgoingMessage::welcome(*sender, welcome));
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::network::{ClientCommand, IncomingMessage};
+use crate::simulation::command_queue::{WorldCommand, WorldCommandQueue};
+use crate::simulation::path::Directories;
+use crate::simulation::rejections::Rejections;
+use crate::simulation::{save, scenario};
+use specs::prelude::*;
+use tracing::warn;
+
+pub struct WorldCommandSystem;
+
+impl<'a> System<'a> for WorldCommandSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Vec<IncomingMessage>>,
+        ReadExpect<'a, Directories>,
+        WriteExpect<'a, WorldCommandQueue>,
+        WriteExpect<'a, Rejections>,
+    );
+
+    /// Queues a world command for each save, load, or scenario request in the
+    /// inbox. The commands are executed between frames since they operate on
+    /// the whole world.
+    fn run(&mut self, data: Self::SystemData) {
+        let (inbox, dirs, mut command_queue, mut rejections) = data;
+
+        for (index, msg) in inbox.iter().enumerate() {
+            let command = match msg.command() {
+                Some(ClientCommand::SaveWorld { name }) => save::path_in(&dirs.saves, name)
+                    .map(WorldCommand::Save)
+                    .map_err(|err| err.to_string()),
+                Some(ClientCommand::LoadWorld { name }) => save::path_in(&dirs.saves, name)
+                    .map(WorldCommand::Load)
+                    .map_err(|err| err.to_string()),
+                Some(ClientCommand::LoadScenario { name }) => {
+                    scenario::path_in(&dirs.scenarios, name)
+                        .map(WorldCommand::LoadScenario)
+                        .map_err(|err| err.to_string())
+                }
+                _ => continue,
+            };
+            match command {
+                Ok(command) => command_queue.push(index, command),
+                Err(err) => {
+                    match msg.sender() {
+                        Some(sender) => warn!("Rejected request from {}: {}", sender, err),
+                        None => warn!("Rejected request: {}", err),
+                    }
+                    rejections.reject(index, err);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use specs::{prelude::*, RunningTime};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{trace_span, Span};
+
+/// Time taken by each system that ran since the timings were last taken.
+/// Clones share the same timings.
+#[derive(Clone, Debug)]
+pub struct SystemTimings {
+    durations: Arc<Mutex<Vec<(&'static str, Duration)>>>,
+
+    /// Span of the frame during which the systems run. Systems run on the
+    /// dispatcher's thread pool, so their spans can't inherit it.
+    frame_span: Arc<Mutex<Span>>,
+}
+
+impl Default for SystemTimings {
+    fn default() -> SystemTimings {
+        SystemTimings {
+            durations: Arc::default(),
+            frame_span: Arc::new(Mutex::new(Span::none())),
+        }
+    }
+}
+
+impl SystemTimings {
+    /// Sets the span under which the spans of the systems are entered.
+    pub fn set_frame_span(&self, span: Span) {
+        *self.frame_span.lock().unwrap() = span;
+    }
+
+    fn record(&self, name: &'static str, duration: Duration) {
+        self.durations.lock().unwrap().push((name, duration));
+    }
+
+    /// Takes the timings recorded since the last call.
+    pub fn take(&self) -> Vec<(&'static str, Duration)> {
+        self.durations.lock().unwrap().drain(..).collect()
+    }
+}
+
+/// Runs a system and records how long it takes.
+pub struct Timed<S> {
+    system: S,
+    name: &'static str,
+    timings: SystemTimings,
+}
+
+impl<'a, S> System<'a> for Timed<S>
+where
+    S: System<'a>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let parent = self.timings.frame_span.lock().unwrap().clone();
+        let span = trace_span!(parent: &parent, "system", system = self.name);
+        let _enter = span.enter();
+        let start = Instant::now();
+        self.system.run(data);
+        self.timings.record(self.name, start.elapsed());
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+}
+
+/// Builds a dispatcher whose systems record how long they take. Systems are
+/// added the same way as with a `DispatcherBuilder`.
+pub struct TimedDispatcherBuilder<'a, 'b> {
+    builder: DispatcherBuilder<'a, 'b>,
+    timings: SystemTimings,
+}
+
+impl<'a, 'b> TimedDispatcherBuilder<'a, 'b> {
+    pub fn new(timings: SystemTimings) -> TimedDispatcherBuilder<'a, 'b> {
+        TimedDispatcherBuilder {
+            builder: DispatcherBuilder::new(),
+            timings,
+        }
+    }
+
+    pub fn with<S>(mut self, system: S, name: &'static str, deps: &[&str]) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a,
+    {
+        let timed = Timed {
+            system,
+            name,
+            timings: self.timings.clone(),
+        };
+        self.builder = self.builder.with(timed, name, deps);
+        self
+    }
+
+    pub fn build(self) -> Dispatcher<'a, 'b> {
+        self.builder.build()
+    }
+}
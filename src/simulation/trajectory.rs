@@ -0,0 +1,123 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// A row of a trajectory file with the state of one agent during one frame.
+#[derive(Clone, Copy, Debug)]
+pub struct TrajectoryRow {
+    pub frame: u64,
+
+    /// Identifier of the agent, which is kept when the world is saved and
+    /// loaded.
+    pub id: u64,
+
+    pub x: f32,
+    pub y: f32,
+
+    /// Heading angle in radians.
+    pub heading: f32,
+
+    /// Velocity in meters per second.
+    pub vx: f32,
+    pub vy: f32,
+
+    pub behavior: &'static str,
+}
+
+/// The file to which agent trajectories are written, if there is one.
+#[derive(Default)]
+pub struct Trajectories {
+    pub writer: Option<TrajectoryWriter>,
+}
+
+/// Writes the state of every agent to a CSV file every `interval` frames.
+pub struct TrajectoryWriter {
+    writer: BufWriter<File>,
+    interval: u64,
+
+    /// Number of the next frame to write.
+    next_frame: u64,
+}
+
+impl TrajectoryWriter {
+    /// Creates a CSV file at the given path and writes its header.
+    pub fn create(path: &Path, interval: u64) -> io::Result<TrajectoryWriter> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,id,x,y,heading,vx,vy,behavior")?;
+        Ok(TrajectoryWriter {
+            writer,
+            interval: interval.max(1),
+            next_frame: 0,
+        })
+    }
+
+    /// Returns true if the frame should be written. Frames that are skipped
+    /// because the simulation fell behind don't shift the sampling.
+    pub fn is_due(&self, frame: u64) -> bool {
+        frame >= self.next_frame
+    }
+
+    /// Writes the rows of a frame and schedules the next frame to write.
+    pub fn write_frame<I>(&mut self, frame: u64, rows: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = TrajectoryRow>,
+    {
+        for row in rows {
+            writeln!(
+                self.writer,
+                "{},{},{},{},{},{},{},{}",
+                row.frame, row.id, row.x, row.y, row.heading, row.vx, row.vy, row.behavior
+            )?;
+        }
+        self.next_frame = (frame / self.interval + 1) * self.interval;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrajectoryRow, TrajectoryWriter};
+    use crate::test_util::temp_path;
+    use std::fs;
+
+    #[test]
+    fn frames_are_sampled_at_the_interval() {
+        let path = temp_path("frames-are-sampled-at-the-interval.csv");
+        let mut writer = TrajectoryWriter::create(&path, 3).unwrap();
+        for frame in [0, 1, 2, 4, 5, 6] {
+            if writer.is_due(frame) {
+                let row = TrajectoryRow {
+                    frame,
+                    id: 7,
+                    x: 1.0,
+                    y: 2.5,
+                    heading: 0.5,
+                    vx: 0.0,
+                    vy: -1.0,
+                    behavior: "walking",
+                };
+                writer.write_frame(frame, vec![row]).unwrap();
+            }
+        }
+        writer.flush().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let frames: Vec<&str> = contents
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap())
+            .collect();
+        assert_eq!(frames, ["0", "4", "6"]);
+        assert_eq!(contents.lines().nth(1), Some("0,7,1,2.5,0.5,0,-1,walking"));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::geometry::BoundingBox;
+use nalgebra::Vector2;
+
+/// Spatial layout of the world.
+#[derive(Clone, Debug)]
+pub struct WorldMap {
+    /// Area in which agents may move.
+    pub bounds: BoundingBox,
+
+    /// Width and height of a square grid cell in meters.
+    pub cell_size: f32,
+
+    /// Areas inside the bounds that agents may not enter.
+    pub obstacles: Vec<BoundingBox>,
+}
+
+impl WorldMap {
+    /// Gets the number of columns and rows of grid cells needed to cover the
+    /// bounds.
+    pub fn grid_size(&self) -> (usize, usize) {
+        (
+            (self.bounds.width() / self.cell_size).ceil() as usize,
+            (self.bounds.height() / self.cell_size).ceil() as usize,
+        )
+    }
+
+    /// Gets the position of the grid cell that contains the point.
+    pub fn cell_at(&self, pos: Vector2<f32>) -> Option<(usize, usize)> {
+        let x = (pos.x - self.bounds.x_min) / self.cell_size;
+        let y = (pos.y - self.bounds.y_min) / self.cell_size;
+        let (width, height) = self.grid_size();
+        if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if an agent may occupy the point.
+    pub fn is_open(&self, pos: Vector2<f32>) -> bool {
+        self.bounds.contains(pos.x, pos.y)
+            && !self.obstacles.iter().any(|o| o.contains(pos.x, pos.y))
+    }
+}
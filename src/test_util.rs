@@ -0,0 +1,9 @@
+//! Helpers shared by the unit tests.
+
+use std::path::PathBuf;
+
+/// Gets a path in the temporary directory with the given file name that no
+/// other test, or concurrent run of the tests, uses. Nothing is created.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("abm-server-{}-{}", std::process::id(), name))
+}